@@ -0,0 +1,81 @@
+use crate::{Change, Layer};
+
+/// A layer handling events by a closure. Created by `layer_fn`.
+pub struct LayerFn<F>(F);
+
+/// Create a layer from a closure, which is called for every `update`.
+pub fn layer_fn<F>(update: F) -> LayerFn<F> {
+    LayerFn(update)
+}
+
+impl<S, E, F: FnMut(&mut S, &E) -> Change<S, E>> Layer<S, E> for LayerFn<F> {
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        (self.0)(state, event)
+    }
+}
+
+/// A layer handling events and passive updates by closures. Created by `layer_fn_with_passive`.
+pub struct LayerFnWithPassive<U, P> {
+    update: U,
+    passive: P,
+}
+
+/// Create a layer from a closure, which is called for every `update`, and a closure, which is called for every `passive_update`.
+pub fn layer_fn_with_passive<U, P>(update: U, passive: P) -> LayerFnWithPassive<U, P> {
+    LayerFnWithPassive { update, passive }
+}
+
+impl<S, E, U: FnMut(&mut S, &E) -> Change<S, E>, P: FnMut(&mut S, &E)> Layer<S, E>
+    for LayerFnWithPassive<U, P>
+{
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        (self.passive)(state, event)
+    }
+
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        (self.update)(state, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Event, GlobalState};
+    use crate::LayerManager;
+    use alloc::{boxed::Box, rc::Rc, vec};
+    use core::cell::Cell;
+
+    fn top_layer() -> LayerFn<impl FnMut(&mut GlobalState, &Event) -> Change<GlobalState, Event>> {
+        layer_fn(|_state: &mut GlobalState, event: &Event| match event {
+            Event::Input => Change::pass(),
+            Event::Idle => Change::none(),
+            Event::Exit => Change::remove(),
+        })
+    }
+
+    #[test]
+    fn example() {
+        let draws = Rc::new(Cell::new(0));
+        let counter = draws.clone();
+        let main_layer = layer_fn_with_passive(
+            |_state: &mut GlobalState, event: &Event| match event {
+                Event::Input => Change::add_one(top_layer()),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            },
+            move |_state: &mut GlobalState, _event: &Event| counter.set(counter.get() + 1),
+        );
+        let mut manager = LayerManager::new(vec![Box::new(main_layer), Box::new(top_layer())]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+        manager.update(&mut state, Event::Idle);
+
+        while manager.is_active() {
+            manager.update(&mut state, Event::Exit);
+        }
+        assert_eq!(draws.get(), 5);
+    }
+}
@@ -0,0 +1,108 @@
+use super::Intercept;
+use crate::{Change, Layer};
+
+/// A layer, which only handles events when a predicate holds. Created by `LayerExt::filter`.
+pub struct Filtered<L, F> {
+    layer: L,
+    predicate: F,
+}
+
+impl<L, F> Filtered<L, F> {
+    /// Wrap a layer, so it only handles events, for which `predicate` returns `true`, and passes the other events.
+    ///
+    /// Passive updates are always forwarded.
+    pub fn new(layer: L, predicate: F) -> Self {
+        Self { layer, predicate }
+    }
+
+    /// Unwrap the inner layer.
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+}
+
+impl<S, E, L, F: FnMut(&S, &E) -> bool> Intercept<S, E> for Filtered<L, F> {
+    fn before(&mut self, state: &mut S, event: &E) -> Option<Change<S, E>> {
+        if (self.predicate)(state, event) {
+            None
+        } else {
+            Some(Change::pass())
+        }
+    }
+}
+
+impl<S, E, L: Layer<S, E>, F: FnMut(&S, &E) -> bool> Layer<S, E> for Filtered<L, F> {
+    forward_layer!(|this| this.layer => intercepted, passive_update, lifecycle, properties, on_remove_request);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adapters::LayerExt;
+    use crate::tests::{reports_error, reports_output, Event, GlobalState, Reporter};
+    use crate::{Change, Layer, LayerManager};
+    use alloc::{boxed::Box, vec};
+
+    #[derive(Default)]
+    pub struct Game {
+        paused: bool,
+        moves: u32,
+        menu: u32,
+        frames: u32,
+    }
+
+    pub struct Menu;
+
+    impl Layer<Game, Event> for Menu {
+        fn update(&mut self, state: &mut Game, _event: &Event) -> Change<Game, Event> {
+            state.menu += 1;
+            Change::none()
+        }
+    }
+
+    pub struct Player;
+
+    impl Layer<Game, Event> for Player {
+        fn passive_update(&mut self, state: &mut Game, _event: &Event) {
+            state.frames += 1;
+        }
+
+        fn update(&mut self, state: &mut Game, _event: &Event) -> Change<Game, Event> {
+            state.moves += 1;
+            Change::none()
+        }
+    }
+
+    #[test]
+    fn filter() {
+        let mut manager: LayerManager<Game, Event> = LayerManager::new(vec![
+            Box::new(Menu),
+            Box::new(Player.filter(|state: &Game, _event: &Event| !state.paused)),
+        ]);
+        let mut state = Game::default();
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!((state.moves, state.menu), (1, 0));
+
+        state.paused = true;
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Input);
+        assert_eq!((state.moves, state.menu), (1, 2));
+
+        state.paused = false;
+        manager.update(&mut state, Event::Input);
+        assert_eq!((state.moves, state.menu), (2, 2));
+        assert_eq!(state.frames, 4);
+    }
+
+    #[test]
+    fn forwarding() {
+        let mut filtered =
+            Reporter.filter(|_state: &GlobalState, event: &Event| matches!(event, Event::Input));
+        assert!(reports_output(&mut filtered));
+        assert!(reports_error(&mut filtered));
+
+        let mut filtered = Reporter.filter(|_state: &GlobalState, _event: &Event| false);
+        assert!(!reports_output(&mut filtered));
+        assert!(!reports_error(&mut filtered));
+    }
+}
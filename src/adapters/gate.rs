@@ -0,0 +1,149 @@
+use super::Intercept;
+use crate::{Change, Layer};
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+/// A shared flag controlling one or more gated layers. Cloning it creates another handle for the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct Gate(Rc<Cell<bool>>);
+
+impl Gate {
+    /// Create a new gate, which is initially open if `enabled` is `true`.
+    pub fn new(enabled: bool) -> Self {
+        Self(Rc::new(Cell::new(enabled)))
+    }
+
+    /// Checks if the gate is open.
+    pub fn is_enabled(&self) -> bool {
+        self.0.get()
+    }
+
+    /// Open or close the gate.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.set(enabled)
+    }
+
+    /// Switch the gate between open and closed.
+    pub fn toggle(&self) {
+        self.0.set(!self.0.get())
+    }
+}
+
+/// A layer, which only participates while its gate is open. Created by `LayerExt::gated`.
+pub struct GatedLayer<L> {
+    layer: L,
+    gate: Gate,
+}
+
+impl<L> GatedLayer<L> {
+    /// Wrap a layer, so it's only updated while `gate` is open.
+    ///
+    /// While the gate is closed, the layer passes all events and isn't updated passively.
+    pub fn new(layer: L, gate: Gate) -> Self {
+        Self { layer, gate }
+    }
+
+    /// The gate controlling this layer.
+    pub fn gate(&self) -> &Gate {
+        &self.gate
+    }
+}
+
+impl<S, E, L> Intercept<S, E> for GatedLayer<L> {
+    fn before(&mut self, _state: &mut S, _event: &E) -> Option<Change<S, E>> {
+        if self.gate.is_enabled() {
+            None
+        } else {
+            Some(Change::pass())
+        }
+    }
+}
+
+impl<S, E, L: Layer<S, E>> Layer<S, E> for GatedLayer<L> {
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        if self.gate.is_enabled() {
+            self.layer.passive_update(state, event)
+        }
+    }
+
+    fn needs_passive(&self) -> bool {
+        self.gate.is_enabled() && self.layer.needs_passive()
+    }
+
+    forward_layer!(|this| this.layer =>
+        intercepted, lifecycle, on_remove_request,
+        priority, tags, z_order, enabled, set_enabled, sticky, is_persistent, is_opaque, name, layer_type_id, save,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::LayerExt;
+    use crate::tests::{reports_error, reports_output, Event, Reporter};
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec};
+
+    #[derive(Default)]
+    pub struct Counts {
+        handled: u32,
+        fallback: u32,
+        drawn: u32,
+    }
+
+    pub struct Overlay;
+
+    impl Layer<Counts, Event> for Overlay {
+        fn passive_update(&mut self, state: &mut Counts, _event: &Event) {
+            state.drawn += 1;
+        }
+
+        fn update(&mut self, state: &mut Counts, _event: &Event) -> Change<Counts, Event> {
+            state.handled += 1;
+            Change::none()
+        }
+    }
+
+    pub struct Fallback;
+
+    impl Layer<Counts, Event> for Fallback {
+        fn update(&mut self, state: &mut Counts, _event: &Event) -> Change<Counts, Event> {
+            state.fallback += 1;
+            Change::none()
+        }
+    }
+
+    #[test]
+    fn gate() {
+        let gate = Gate::new(false);
+        let mut manager: LayerManager<Counts, Event> = LayerManager::new(vec![
+            Box::new(Fallback),
+            Box::new(Overlay.gated(gate.clone())),
+        ]);
+        let mut state = Counts::default();
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!((state.handled, state.fallback, state.drawn), (0, 1, 0));
+
+        gate.toggle();
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Input);
+        assert_eq!((state.handled, state.fallback, state.drawn), (2, 1, 2));
+
+        gate.set_enabled(false);
+        manager.update(&mut state, Event::Input);
+        assert_eq!((state.handled, state.fallback, state.drawn), (2, 2, 2));
+    }
+
+    #[test]
+    fn forwarding() {
+        let gate = Gate::new(false);
+        let mut gated = Reporter.gated(gate.clone());
+        assert!(!reports_output(&mut gated));
+        assert!(!reports_error(&mut gated));
+
+        gate.toggle();
+        assert!(reports_output(&mut gated));
+        assert!(reports_error(&mut gated));
+    }
+}
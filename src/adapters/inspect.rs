@@ -0,0 +1,142 @@
+use super::Intercept;
+use crate::{Change, ChangeAction, Layer};
+
+/// A call received by an inspected layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InspectEvent {
+    /// The layer has been updated and returned a change.
+    Update {
+        /// The number of layers the change adds.
+        added: usize,
+        /// The action of the change.
+        action: ChangeAction,
+    },
+    /// The layer has been updated passively.
+    Passive,
+    /// The layer has been added to a layer manager.
+    Attach,
+    /// The layer has been removed from a layer manager.
+    Detach,
+    /// The layer has been covered by another layer.
+    Cover,
+    /// The layer has become the top layer again.
+    Uncover,
+}
+
+/// A layer, which reports all calls it receives to a callback. Created by `LayerExt::inspect`.
+pub struct Inspect<L> {
+    layer: L,
+    callback: fn(&InspectEvent),
+}
+
+impl<L> Inspect<L> {
+    /// Wrap a layer, so `callback` is called after every call of the layer.
+    ///
+    /// The callback is a plain function, so no allocation is needed. The behavior of the layer isn't changed.
+    pub fn new(layer: L, callback: fn(&InspectEvent)) -> Self {
+        Self { layer, callback }
+    }
+
+    /// Unwrap the inner layer.
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+}
+
+impl<S, E, L> Intercept<S, E> for Inspect<L> {
+    fn after(&mut self, change: Change<S, E>) -> Change<S, E> {
+        (self.callback)(&InspectEvent::Update {
+            added: change.add.len(),
+            action: change.action,
+        });
+        change
+    }
+}
+
+impl<S, E, L: Layer<S, E>> Layer<S, E> for Inspect<L> {
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        self.layer.passive_update(state, event);
+        (self.callback)(&InspectEvent::Passive);
+    }
+
+    fn on_attach(&mut self) {
+        self.layer.on_attach();
+        (self.callback)(&InspectEvent::Attach);
+    }
+
+    fn on_detach(&mut self) {
+        self.layer.on_detach();
+        (self.callback)(&InspectEvent::Detach);
+    }
+
+    fn on_cover(&mut self) {
+        self.layer.on_cover();
+        (self.callback)(&InspectEvent::Cover);
+    }
+
+    fn on_uncover(&mut self) {
+        self.layer.on_uncover();
+        (self.callback)(&InspectEvent::Uncover);
+    }
+
+    forward_layer!(|this| this.layer => intercepted, properties, on_remove_request);
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::adapters::LayerExt;
+    use crate::tests::{reports_error, reports_output, Event, GlobalState, MainLayer, Reporter};
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec, vec::Vec};
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<InspectEvent>> = Mutex::new(Vec::new());
+
+    fn record(event: &InspectEvent) {
+        RECORDS.lock().unwrap().push(*event);
+    }
+
+    #[test]
+    fn inspect() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer.inspect(record))]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Idle);
+        manager.truncate(1);
+        manager.update(&mut state, Event::Exit);
+
+        use InspectEvent::*;
+        assert_eq!(
+            RECORDS.lock().unwrap()[..],
+            [
+                Attach,
+                Update {
+                    added: 1,
+                    action: ChangeAction::None
+                },
+                Cover,
+                Passive,
+                Passive,
+                Uncover,
+                Update {
+                    added: 0,
+                    action: ChangeAction::Remove
+                },
+                Detach,
+            ]
+        );
+    }
+
+    #[test]
+    fn forwarding() {
+        fn ignore(_event: &InspectEvent) {}
+
+        let mut inspected = Reporter.inspect(ignore);
+        assert!(reports_output(&mut inspected));
+        assert!(reports_error(&mut inspected));
+    }
+}
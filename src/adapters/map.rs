@@ -0,0 +1,481 @@
+use crate::{Change, Layer, LayerError};
+use alloc::boxed::Box;
+use core::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+};
+
+/// A layer, which handles a part of a bigger state. Created by `LayerExt::map_state`.
+pub struct MapState<L, F, S> {
+    layer: L,
+    project: F,
+    state: PhantomData<fn(&mut S)>,
+}
+
+impl<L, F, S> MapState<L, F, S> {
+    /// Wrap a layer, so it can be used over any state, from which `project` returns the state of the layer.
+    pub fn new(layer: L, project: F) -> Self {
+        Self {
+            layer,
+            project,
+            state: PhantomData,
+        }
+    }
+
+    /// Unwrap the inner layer.
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+}
+
+impl<L, F: Clone, S> MapState<L, F, S> {
+    fn wrap<T: 'static, E: 'static>(&self, change: Change<S, E>) -> Change<T, E>
+    where
+        S: 'static,
+        F: FnMut(&mut T) -> &mut S + 'static,
+    {
+        let Change {
+            add,
+            action,
+            placement,
+            dirty,
+        } = change;
+        Change {
+            add: add
+                .into_iter()
+                .map(|layer| -> Box<dyn Layer<T, E>> {
+                    Box::new(MapState::new(layer, self.project.clone()))
+                })
+                .collect(),
+            action,
+            placement,
+            dirty,
+        }
+    }
+}
+
+impl<T: 'static, S: 'static, E: 'static, L: Layer<S, E>, F> Layer<T, E> for MapState<L, F, S>
+where
+    F: FnMut(&mut T) -> &mut S + Clone + 'static,
+{
+    fn passive_update(&mut self, state: &mut T, event: &E) {
+        self.layer.passive_update((self.project)(state), event)
+    }
+
+    fn update(&mut self, state: &mut T, event: &E) -> Change<T, E> {
+        let change = self.layer.update((self.project)(state), event);
+        self.wrap(change)
+    }
+
+    fn update_output(&mut self, state: &mut T, event: &E) -> (Change<T, E>, Option<Box<dyn Any>>) {
+        let (change, output) = self.layer.update_output((self.project)(state), event);
+        (self.wrap(change), output)
+    }
+
+    fn on_attach(&mut self) {
+        self.layer.on_attach()
+    }
+
+    fn on_detach(&mut self) {
+        self.layer.on_detach()
+    }
+
+    fn on_cover(&mut self) {
+        self.layer.on_cover()
+    }
+
+    fn on_uncover(&mut self) {
+        self.layer.on_uncover()
+    }
+
+    fn priority(&self) -> i32 {
+        self.layer.priority()
+    }
+
+    fn tags(&self) -> u32 {
+        self.layer.tags()
+    }
+
+    fn z_order(&self) -> i32 {
+        self.layer.z_order()
+    }
+
+    fn needs_passive(&self) -> bool {
+        self.layer.needs_passive()
+    }
+
+    fn enabled(&self) -> bool {
+        self.layer.enabled()
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.layer.set_enabled(enabled)
+    }
+
+    fn sticky(&self) -> bool {
+        self.layer.sticky()
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.layer.is_persistent()
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.layer.is_opaque()
+    }
+
+    fn on_remove_request(&mut self, state: &mut T) -> bool {
+        self.layer.on_remove_request((self.project)(state))
+    }
+
+    fn try_update(&mut self, state: &mut T, event: &E) -> Result<Change<T, E>, LayerError> {
+        let change = self.layer.try_update((self.project)(state), event)?;
+        Ok(self.wrap(change))
+    }
+
+    fn take_event(&mut self, state: &mut T, event: E) -> Result<Change<T, E>, E> {
+        let change = self.layer.take_event((self.project)(state), event)?;
+        Ok(self.wrap(change))
+    }
+
+    fn name(&self) -> &'static str {
+        self.layer.name()
+    }
+
+    fn layer_type_id(&self) -> TypeId
+    where
+        Self: 'static,
+    {
+        self.layer.layer_type_id()
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<alloc::vec::Vec<u8>> {
+        self.layer.save()
+    }
+}
+
+/// A layer, which handles events of a different type. Created by `LayerExt::map_event`.
+pub struct MapEvent<L, F, U> {
+    layer: L,
+    map: F,
+    event: PhantomData<fn(&U)>,
+}
+
+impl<L, F, U> MapEvent<L, F, U> {
+    /// Wrap a layer, so it can be used for any event type, which `map` translates into the event type of the layer.
+    ///
+    /// Events, for which `map` returns `None`, are passed.
+    pub fn new(layer: L, map: F) -> Self {
+        Self {
+            layer,
+            map,
+            event: PhantomData,
+        }
+    }
+
+    /// Unwrap the inner layer.
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+}
+
+impl<L, F: Clone, U> MapEvent<L, F, U> {
+    fn wrap<S: 'static, E: 'static>(&self, change: Change<S, U>) -> Change<S, E>
+    where
+        U: 'static,
+        F: Fn(&E) -> Option<U> + 'static,
+    {
+        let Change {
+            add,
+            action,
+            placement,
+            dirty,
+        } = change;
+        Change {
+            add: add
+                .into_iter()
+                .map(|layer| -> Box<dyn Layer<S, E>> {
+                    Box::new(MapEvent::new(layer, self.map.clone()))
+                })
+                .collect(),
+            action,
+            placement,
+            dirty,
+        }
+    }
+}
+
+impl<S: 'static, E: 'static, U: 'static, L: Layer<S, U>, F> Layer<S, E> for MapEvent<L, F, U>
+where
+    F: Fn(&E) -> Option<U> + Clone + 'static,
+{
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        if let Some(event) = (self.map)(event) {
+            self.layer.passive_update(state, &event)
+        }
+    }
+
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        match (self.map)(event) {
+            Some(event) => {
+                let change = self.layer.update(state, &event);
+                self.wrap(change)
+            }
+            None => Change::pass(),
+        }
+    }
+
+    fn update_output(&mut self, state: &mut S, event: &E) -> (Change<S, E>, Option<Box<dyn Any>>) {
+        match (self.map)(event) {
+            Some(event) => {
+                let (change, output) = self.layer.update_output(state, &event);
+                (self.wrap(change), output)
+            }
+            None => (Change::pass(), None),
+        }
+    }
+
+    fn on_attach(&mut self) {
+        self.layer.on_attach()
+    }
+
+    fn on_detach(&mut self) {
+        self.layer.on_detach()
+    }
+
+    fn on_cover(&mut self) {
+        self.layer.on_cover()
+    }
+
+    fn on_uncover(&mut self) {
+        self.layer.on_uncover()
+    }
+
+    fn priority(&self) -> i32 {
+        self.layer.priority()
+    }
+
+    fn tags(&self) -> u32 {
+        self.layer.tags()
+    }
+
+    fn z_order(&self) -> i32 {
+        self.layer.z_order()
+    }
+
+    fn needs_passive(&self) -> bool {
+        self.layer.needs_passive()
+    }
+
+    fn enabled(&self) -> bool {
+        self.layer.enabled()
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.layer.set_enabled(enabled)
+    }
+
+    fn sticky(&self) -> bool {
+        self.layer.sticky()
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.layer.is_persistent()
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.layer.is_opaque()
+    }
+
+    fn on_remove_request(&mut self, state: &mut S) -> bool {
+        self.layer.on_remove_request(state)
+    }
+
+    fn try_update(&mut self, state: &mut S, event: &E) -> Result<Change<S, E>, LayerError> {
+        match (self.map)(event) {
+            Some(event) => {
+                let change = self.layer.try_update(state, &event)?;
+                Ok(self.wrap(change))
+            }
+            None => Ok(Change::pass()),
+        }
+    }
+
+    fn take_event(&mut self, state: &mut S, event: E) -> Result<Change<S, E>, E> {
+        let change = match (self.map)(&event) {
+            Some(mapped) => match self.layer.take_event(state, mapped) {
+                Ok(change) => change,
+                Err(_) => return Err(event),
+            },
+            None => return Err(event),
+        };
+        Ok(self.wrap(change))
+    }
+
+    fn name(&self) -> &'static str {
+        self.layer.name()
+    }
+
+    fn layer_type_id(&self) -> TypeId
+    where
+        Self: 'static,
+    {
+        self.layer.layer_type_id()
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<alloc::vec::Vec<u8>> {
+        self.layer.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adapters::LayerExt;
+    use crate::tests::Event;
+    use crate::{Change, Layer, LayerManager};
+    use alloc::{boxed::Box, vec};
+
+    #[derive(Default)]
+    pub struct MenuState {
+        opened: u32,
+        selected: u32,
+    }
+
+    #[derive(Default)]
+    pub struct GameState {
+        menu: MenuState,
+        frames: u32,
+    }
+
+    pub struct Menu;
+
+    impl Layer<MenuState, Event> for Menu {
+        fn update(&mut self, state: &mut MenuState, event: &Event) -> Change<MenuState, Event> {
+            match event {
+                Event::Input => {
+                    state.opened += 1;
+                    Change::add_one(Submenu)
+                }
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    pub struct Submenu;
+
+    impl Layer<MenuState, Event> for Submenu {
+        fn update(&mut self, state: &mut MenuState, event: &Event) -> Change<MenuState, Event> {
+            match event {
+                Event::Input => {
+                    state.selected += 1;
+                    Change::none()
+                }
+                Event::Idle => Change::pass(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    pub struct Game;
+
+    impl Layer<GameState, Event> for Game {
+        fn passive_update(&mut self, state: &mut GameState, _event: &Event) {
+            state.frames += 1;
+        }
+
+        fn update(&mut self, _state: &mut GameState, _event: &Event) -> Change<GameState, Event> {
+            Change::none()
+        }
+    }
+
+    #[test]
+    fn map_state() {
+        let mut manager: LayerManager<GameState, Event> = LayerManager::new(vec![
+            Box::new(Game),
+            Box::new(Menu.map_state(|state: &mut GameState| &mut state.menu)),
+        ]);
+        let mut state = GameState::default();
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+        assert_eq!(state.menu.opened, 1);
+
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(manager.len(), 3);
+        assert_eq!(state.menu.selected, 1);
+
+        manager.update(&mut state, Event::Exit);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 1);
+        assert_eq!(state.frames, 5);
+    }
+
+    pub enum UiEvent {
+        Confirm,
+        Close,
+    }
+
+    fn translate(event: &Event) -> Option<UiEvent> {
+        match event {
+            Event::Input => Some(UiEvent::Confirm),
+            Event::Exit => Some(UiEvent::Close),
+            Event::Idle => None,
+        }
+    }
+
+    #[derive(Default)]
+    pub struct UiState {
+        confirmed: u32,
+        unmapped: u32,
+    }
+
+    pub struct Page;
+
+    impl Layer<UiState, UiEvent> for Page {
+        fn update(&mut self, state: &mut UiState, event: &UiEvent) -> Change<UiState, UiEvent> {
+            match event {
+                UiEvent::Confirm => {
+                    state.confirmed += 1;
+                    Change::replace_one(Page)
+                }
+                UiEvent::Close => Change::remove(),
+            }
+        }
+    }
+
+    pub struct Fallback;
+
+    impl Layer<UiState, Event> for Fallback {
+        fn update(&mut self, state: &mut UiState, event: &Event) -> Change<UiState, Event> {
+            if let Event::Idle = event {
+                state.unmapped += 1;
+            }
+            Change::none()
+        }
+    }
+
+    #[test]
+    fn map_event() {
+        let mut manager: LayerManager<UiState, Event> = LayerManager::new(vec![
+            Box::new(Fallback),
+            Box::new(Page.map_event(translate)),
+        ]);
+        let mut state = UiState::default();
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(state.unmapped, 1);
+
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 2);
+        assert_eq!(state.confirmed, 2);
+        assert_eq!(state.unmapped, 1);
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 1);
+    }
+}
@@ -0,0 +1,93 @@
+//! Adapters for building layers out of other things or modifying existing layers.
+
+mod closure;
+mod filter;
+mod gate;
+mod inspect;
+mod map;
+mod once;
+mod router;
+mod then;
+mod throttle;
+mod timeout;
+mod typed;
+
+pub use self::closure::{layer_fn, layer_fn_with_passive, LayerFn, LayerFnWithPassive};
+pub use self::filter::Filtered;
+pub use self::gate::{Gate, GatedLayer};
+pub use self::inspect::{Inspect, InspectEvent};
+pub use self::map::{MapEvent, MapState};
+pub use self::once::{once_layer, OnceLayer};
+pub use self::router::{Router, Routes};
+pub use self::then::Then;
+pub use self::throttle::{Debounce, Throttle};
+pub use self::timeout::TimeoutLayer;
+pub use self::typed::{LayerT, Typed};
+
+use crate::{Change, Layer};
+
+/// The part of an adapter, which changes how the inner layer handles events.
+///
+/// Implemented by adapters using the `intercepted` methods of `forward_layer!`, so all update methods behave the same.
+pub(crate) trait Intercept<S, E> {
+    /// Called before the inner layer is updated. Returning a change skips the inner layer.
+    fn before(&mut self, _state: &mut S, _event: &E) -> Option<Change<S, E>> {
+        None
+    }
+
+    /// Called with the change returned by the inner layer.
+    fn after(&mut self, change: Change<S, E>) -> Change<S, E> {
+        change
+    }
+}
+
+/// Methods for wrapping layers into adapters, available for every layer.
+pub trait LayerExt<S, E>: Layer<S, E> + Sized {
+    /// Use the layer over a bigger state, from which `project` returns the state of the layer.
+    ///
+    /// Layers added by the inner layer are wrapped the same way.
+    fn map_state<T, F: FnMut(&mut T) -> &mut S>(self, project: F) -> MapState<Self, F, S> {
+        MapState::new(self, project)
+    }
+
+    /// Use the layer for a different event type, which `map` translates into the event type of the layer.
+    ///
+    /// Events, for which `map` returns `None`, are passed. Layers added by the inner layer are wrapped the same way.
+    fn map_event<T, F: Fn(&T) -> Option<E>>(self, map: F) -> MapEvent<Self, F, E> {
+        MapEvent::new(self, map)
+    }
+
+    /// Only handle events, for which `predicate` returns `true`, and pass the other events.
+    ///
+    /// Passive updates are always forwarded.
+    fn filter<F: FnMut(&S, &E) -> bool>(self, predicate: F) -> Filtered<Self, F> {
+        Filtered::new(self, predicate)
+    }
+
+    /// Only participate while `gate` is open.
+    fn gated(self, gate: Gate) -> GatedLayer<Self> {
+        GatedLayer::new(self, gate)
+    }
+
+    /// Report all calls of the layer to `callback`.
+    fn inspect(self, callback: fn(&InspectEvent)) -> Inspect<Self> {
+        Inspect::new(self, callback)
+    }
+
+    /// Only handle the first of every `every` events, and pass the others.
+    fn throttle(self, every: usize) -> Throttle<Self> {
+        Throttle::new(self, every)
+    }
+
+    /// Only handle an event once `count` events, which are the same according to `same`, have been received in a row, and pass the others.
+    fn debounce(self, count: usize, same: fn(&E, &E) -> bool) -> Debounce<Self, E> {
+        Debounce::new(self, count, same)
+    }
+
+    /// Replace the layer with `next` in place, when it removes itself.
+    fn then<B: Layer<S, E>>(self, next: B) -> Then<Self, B> {
+        Then::new(self, next)
+    }
+}
+
+impl<S, E, L: Layer<S, E>> LayerExt<S, E> for L {}
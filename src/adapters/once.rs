@@ -0,0 +1,66 @@
+use crate::{Change, Layer};
+
+/// A layer handling only a single event by a closure, and removing itself afterwards. Created by `once_layer`.
+pub struct OnceLayer<F>(Option<F>);
+
+/// Create a layer from a closure, which is called for the first `update` only.
+///
+/// The layer removes itself after calling the closure, additionally to the change returned by it.
+/// In case it's updated again, it passes the event.
+pub fn once_layer<F>(update: F) -> OnceLayer<F> {
+    OnceLayer(Some(update))
+}
+
+impl<S, E, F: FnOnce(&mut S, &E) -> Change<S, E>> Layer<S, E> for OnceLayer<F> {
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        match self.0.take() {
+            Some(update) => update(state, event).removing(),
+            None => Change::pass(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Event, GlobalState, TopLayer};
+    use crate::{ChangeAction, LayerManager};
+    use alloc::{boxed::Box, rc::Rc, vec};
+    use core::cell::Cell;
+
+    #[test]
+    fn once() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let reward = once_layer(move |_state: &mut GlobalState, _event: &Event| {
+            counter.set(counter.get() + 1);
+            Change::add_one(TopLayer)
+        });
+        let mut manager = LayerManager::new(vec![Box::new(TopLayer), Box::new(reward)]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(calls.get(), 1);
+        assert_eq!(manager.len(), 2);
+        assert_eq!(
+            manager
+                .iter()
+                .map(|layer| layer.name())
+                .collect::<vec::Vec<_>>()[..],
+            [core::any::type_name::<TopLayer>(); 2]
+        );
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn called_again() {
+        let mut layer = once_layer(|_state: &mut GlobalState, _event: &Event| Change::none());
+
+        let change = layer.update(&mut GlobalState, &Event::Idle);
+        assert_eq!(change.action, ChangeAction::Remove);
+        let change = layer.update(&mut GlobalState, &Event::Idle);
+        assert_eq!(change.action, ChangeAction::Pass);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{Change, ChangeAction, Layer, LayerError};
+use alloc::{boxed::Box, vec::Vec};
+use core::any::Any;
+
+/// Pairs of a predicate and a child layer, which receives the events matching the predicate.
+pub type Routes<S, E> = Vec<(fn(&E) -> bool, Box<dyn Layer<S, E>>)>;
+
+struct Route<S, E> {
+    matches: fn(&E) -> bool,
+    layer: Box<dyn Layer<S, E>>,
+}
+
+/// A layer owning multiple child layers, which dispatches each event to the first child accepting it.
+pub struct Router<S, E> {
+    routes: Vec<Route<S, E>>,
+}
+
+impl<S, E> Router<S, E> {
+    /// Create a router from pairs of a predicate and a child layer.
+    ///
+    /// An event is dispatched to the first child, whose predicate returns `true` for the event, and passed if there is none.
+    pub fn new(routes: Routes<S, E>) -> Self {
+        Self {
+            routes: routes
+                .into_iter()
+                .map(|(matches, layer)| Route { matches, layer })
+                .collect(),
+        }
+    }
+
+    /// Add another child, which receives the events matching `matches`, unless an earlier child accepts them.
+    pub fn route(mut self, matches: fn(&E) -> bool, layer: Box<dyn Layer<S, E>>) -> Self {
+        self.routes.push(Route { matches, layer });
+        self
+    }
+
+    /// The number of children.
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Checks if the router has no children.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// The index of the child receiving `event`, or the change of the router, if there is none.
+    fn select(&self, event: &E) -> Result<usize, Change<S, E>> {
+        if self.routes.is_empty() {
+            return Err(Change::remove());
+        }
+        self.routes
+            .iter()
+            .position(|route| (route.matches)(event))
+            .ok_or_else(Change::pass)
+    }
+
+    /// Turn the change of the child at `index` into the change of the router.
+    fn finish(&mut self, index: usize, mut change: Change<S, E>) -> Change<S, E> {
+        if change.action == ChangeAction::Remove {
+            self.routes.remove(index).layer.on_detach();
+            if !self.routes.is_empty() {
+                change.action = ChangeAction::None;
+            }
+        }
+        change
+    }
+}
+
+/// Children removing themselves are removed from the router, and the router removes itself, when no children are left.
+/// Layers added by children are added to the stack above the router. All other actions apply to the router.
+impl<S, E> Layer<S, E> for Router<S, E> {
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        for route in &mut self.routes {
+            route.layer.passive_update(state, event);
+        }
+    }
+
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        match self.select(event) {
+            Ok(index) => {
+                let change = self.routes[index].layer.update(state, event);
+                self.finish(index, change)
+            }
+            Err(change) => change,
+        }
+    }
+
+    fn update_output(&mut self, state: &mut S, event: &E) -> (Change<S, E>, Option<Box<dyn Any>>) {
+        match self.select(event) {
+            Ok(index) => {
+                let (change, output) = self.routes[index].layer.update_output(state, event);
+                (self.finish(index, change), output)
+            }
+            Err(change) => (change, None),
+        }
+    }
+
+    fn try_update(&mut self, state: &mut S, event: &E) -> Result<Change<S, E>, LayerError> {
+        match self.select(event) {
+            Ok(index) => {
+                let change = self.routes[index].layer.try_update(state, event)?;
+                Ok(self.finish(index, change))
+            }
+            Err(change) => Ok(change),
+        }
+    }
+
+    fn take_event(&mut self, state: &mut S, event: E) -> Result<Change<S, E>, E> {
+        match self.select(&event) {
+            Ok(index) => {
+                let change = self.routes[index].layer.take_event(state, event)?;
+                Ok(self.finish(index, change))
+            }
+            Err(change) if change.action == ChangeAction::Pass => Err(event),
+            Err(change) => Ok(change),
+        }
+    }
+
+    fn on_attach(&mut self) {
+        for route in &mut self.routes {
+            route.layer.on_attach();
+        }
+    }
+
+    fn on_detach(&mut self) {
+        for route in &mut self.routes {
+            route.layer.on_detach();
+        }
+    }
+
+    fn on_cover(&mut self) {
+        for route in &mut self.routes {
+            route.layer.on_cover();
+        }
+    }
+
+    fn on_uncover(&mut self) {
+        for route in &mut self.routes {
+            route.layer.on_uncover();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{calls, CallKind, CallLog, RecordingLayer};
+    use crate::tests::{reports_error, reports_output, Event, GlobalState, Reporter};
+    use crate::LayerManager;
+    use alloc::vec;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Input {
+        Key(char),
+        Gamepad(u8),
+        Tick,
+    }
+
+    fn is_key(input: &Input) -> bool {
+        matches!(input, Input::Key(_))
+    }
+
+    fn is_gamepad(input: &Input) -> bool {
+        matches!(input, Input::Gamepad(_))
+    }
+
+    fn updates(log: &CallLog<Input>, layer: &str) -> vec::Vec<Input> {
+        calls(log, Some(layer), CallKind::Update)
+            .iter()
+            .map(|call| call.event)
+            .collect()
+    }
+
+    #[test]
+    fn routing() {
+        let log = CallLog::default();
+        let router = Router::new(vec![(
+            is_key as fn(&Input) -> bool,
+            Box::new(RecordingLayer::cloning("text", &log)) as Box<dyn Layer<(), Input>>,
+        )])
+        .route(
+            is_gamepad,
+            Box::new(
+                RecordingLayer::cloning("navigation", &log)
+                    .with_script(vec![Change::none(), Change::remove()]),
+            ),
+        );
+        let mut manager = LayerManager::new(vec![
+            Box::new(RecordingLayer::cloning("below", &log)),
+            Box::new(router),
+        ]);
+
+        for &input in &[Input::Key('a'), Input::Gamepad(1), Input::Tick] {
+            manager.update(&mut (), input);
+        }
+        assert_eq!(updates(&log, "text"), [Input::Key('a')]);
+        assert_eq!(updates(&log, "navigation"), [Input::Gamepad(1)]);
+        assert_eq!(updates(&log, "below"), [Input::Tick]);
+        assert_eq!(calls(&log, None, CallKind::Passive).len(), 9);
+        log.borrow_mut().clear();
+
+        manager.update(&mut (), Input::Gamepad(2));
+        assert_eq!(manager.len(), 2);
+        manager.update(&mut (), Input::Gamepad(3));
+        assert_eq!(updates(&log, "below"), [Input::Gamepad(3)]);
+    }
+
+    #[test]
+    fn self_removal() {
+        let log = CallLog::default();
+        let router = Router::new(vec![(
+            is_key as fn(&Input) -> bool,
+            Box::new(RecordingLayer::cloning("text", &log).with_script(vec![Change::remove()]))
+                as Box<dyn Layer<(), Input>>,
+        )]);
+        let mut manager = LayerManager::new(vec![Box::new(router)]);
+
+        manager.update(&mut (), Input::Tick);
+        assert_eq!(manager.len(), 1);
+        manager.update(&mut (), Input::Key('q'));
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn empty() {
+        let mut manager = LayerManager::new(vec![Box::new(Router::<(), Input>::new(vec![]))]);
+
+        manager.update(&mut (), Input::Tick);
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn forwarding() {
+        fn is_input(event: &Event) -> bool {
+            matches!(event, Event::Input)
+        }
+
+        fn is_exit(event: &Event) -> bool {
+            matches!(event, Event::Exit)
+        }
+
+        let mut router =
+            Router::<GlobalState, Event>::new(vec![]).route(is_input, Box::new(Reporter));
+        assert!(reports_output(&mut router));
+        assert!(reports_error(&mut router));
+
+        let mut router =
+            Router::<GlobalState, Event>::new(vec![]).route(is_exit, Box::new(Reporter));
+        assert!(!reports_output(&mut router));
+        assert!(!reports_error(&mut router));
+    }
+}
@@ -0,0 +1,149 @@
+use super::Intercept;
+use crate::{Change, ChangeAction, Layer};
+use core::any::TypeId;
+
+/// A layer, which behaves like one layer until it removes itself, and then like another layer. Created by `LayerExt::then`.
+pub struct Then<A, B> {
+    first: Option<A>,
+    second: B,
+}
+
+impl<A, B> Then<A, B> {
+    /// Combine two layers, so `second` replaces `first` in place, when `first` removes itself.
+    ///
+    /// Only `Remove` of `first` is intercepted. Layers added by `first` are added to the stack as usual, and `Clear` still clears the stack.
+    /// The combined layer removes itself when `second` removes itself.
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first: Some(first),
+            second,
+        }
+    }
+
+    /// Checks if the first layer has finished, so the second layer is active.
+    pub fn is_second(&self) -> bool {
+        self.first.is_none()
+    }
+}
+
+impl<A, B> Then<A, B> {
+    fn current<S, E>(&self) -> &dyn Layer<S, E>
+    where
+        A: Layer<S, E>,
+        B: Layer<S, E>,
+    {
+        match &self.first {
+            Some(first) => first,
+            None => &self.second,
+        }
+    }
+
+    fn current_mut<S, E>(&mut self) -> &mut dyn Layer<S, E>
+    where
+        A: Layer<S, E>,
+        B: Layer<S, E>,
+    {
+        match &mut self.first {
+            Some(first) => first,
+            None => &mut self.second,
+        }
+    }
+}
+
+impl<S, E, A: Layer<S, E>, B: Layer<S, E>> Intercept<S, E> for Then<A, B> {
+    fn after(&mut self, mut change: Change<S, E>) -> Change<S, E> {
+        if change.action != ChangeAction::Remove {
+            return change;
+        }
+        if let Some(mut first) = self.first.take() {
+            first.on_detach();
+            self.second.on_attach();
+            change.action = ChangeAction::None;
+        }
+        change
+    }
+}
+
+impl<S, E, A: Layer<S, E>, B: Layer<S, E>> Layer<S, E> for Then<A, B> {
+    forward_layer!(|this| this.current::<S, E>(), this.current_mut::<S, E>() =>
+        intercepted, passive_update, lifecycle, on_remove_request,
+        priority, tags, z_order, needs_passive, enabled, set_enabled, sticky, is_persistent, is_opaque, name, save,
+    );
+
+    fn layer_type_id(&self) -> TypeId
+    where
+        Self: 'static,
+    {
+        match &self.first {
+            Some(first) => first.layer_type_id(),
+            None => self.second.layer_type_id(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adapters::LayerExt;
+    use crate::tests::{
+        reports_error, reports_output, Event, GlobalState, MainLayer, Reporter, TopLayer,
+    };
+    use crate::{Change, Layer, LayerManager};
+    use alloc::{boxed::Box, vec, vec::Vec};
+
+    pub struct Stage(&'static str);
+
+    impl Layer<Vec<&'static str>, Event> for Stage {
+        fn update(
+            &mut self,
+            state: &mut Vec<&'static str>,
+            event: &Event,
+        ) -> Change<Vec<&'static str>, Event> {
+            state.push(self.0);
+            match event {
+                Event::Input => Change::add_one(Stage("popup")),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    #[test]
+    fn three_stages() {
+        let flow = Stage("splash").then(Stage("title")).then(Stage("menu"));
+        let mut manager = LayerManager::new(vec![Box::new(flow)]);
+        let mut state = Vec::new();
+
+        for event in [
+            Event::Idle,
+            Event::Exit,
+            Event::Idle,
+            Event::Exit,
+            Event::Idle,
+        ] {
+            manager.update(&mut state, event);
+            assert_eq!(manager.len(), 1);
+        }
+        assert_eq!(state[..], ["splash", "splash", "title", "title", "menu"]);
+        state.clear();
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 2);
+        manager.update(&mut state, Event::Exit);
+        manager.update(&mut state, Event::Exit);
+        assert!(manager.is_empty());
+        assert_eq!(state[..], ["menu", "popup", "menu"]);
+    }
+
+    #[test]
+    fn forwarding() {
+        let mut first = Reporter.then(MainLayer);
+        assert!(reports_output(&mut first));
+        assert!(reports_error(&mut first));
+
+        let mut second = TopLayer.then(Reporter);
+        second.update(&mut GlobalState, &Event::Exit);
+        assert!(second.is_second());
+        assert!(reports_output(&mut second));
+        assert!(reports_error(&mut second));
+    }
+}
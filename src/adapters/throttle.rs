@@ -0,0 +1,200 @@
+use super::Intercept;
+use crate::{Change, Layer};
+
+/// A layer, which only handles one of every few events. Created by `LayerExt::throttle`.
+pub struct Throttle<L> {
+    layer: L,
+    every: usize,
+    seen: usize,
+    swallow: bool,
+}
+
+impl<L> Throttle<L> {
+    /// Wrap a layer, so only the first of every `every` events dispatched to it is handled, and the others are passed.
+    ///
+    /// Passive updates are always forwarded.
+    pub fn new(layer: L, every: usize) -> Self {
+        Self {
+            layer,
+            every: every.max(1),
+            seen: 0,
+            swallow: false,
+        }
+    }
+
+    /// Like `new`, but the suppressed events are consumed instead of passed.
+    pub fn swallowing(layer: L, every: usize) -> Self {
+        Self {
+            swallow: true,
+            ..Self::new(layer, every)
+        }
+    }
+}
+
+impl<S, E, L> Intercept<S, E> for Throttle<L> {
+    fn before(&mut self, _state: &mut S, _event: &E) -> Option<Change<S, E>> {
+        let forward = self.seen == 0;
+        self.seen = (self.seen + 1) % self.every;
+        if forward {
+            None
+        } else if self.swallow {
+            Some(Change::none())
+        } else {
+            Some(Change::pass())
+        }
+    }
+}
+
+impl<S, E, L: Layer<S, E>> Layer<S, E> for Throttle<L> {
+    forward_layer!(|this| this.layer => intercepted, passive_update, lifecycle, properties, on_remove_request);
+}
+
+/// A layer, which only handles an event after it has been repeated a few times. Created by `LayerExt::debounce`.
+pub struct Debounce<L, E> {
+    layer: L,
+    count: usize,
+    same: fn(&E, &E) -> bool,
+    last: Option<E>,
+    repeated: usize,
+}
+
+impl<L, E> Debounce<L, E> {
+    /// Wrap a layer, so an event is only handled once `count` events, which are the same according to `same`, have been dispatched to it in a row.
+    /// The other events are passed. After handling an event, counting starts again.
+    ///
+    /// Events are compared with the previous event dispatched to this layer, so a clone of it is stored, which requires `E: Clone`.
+    /// Passive updates are always forwarded.
+    pub fn new(layer: L, count: usize, same: fn(&E, &E) -> bool) -> Self {
+        Self {
+            layer,
+            count: count.max(1),
+            same,
+            last: None,
+            repeated: 0,
+        }
+    }
+}
+
+impl<S, E: Clone, L> Intercept<S, E> for Debounce<L, E> {
+    fn before(&mut self, _state: &mut S, event: &E) -> Option<Change<S, E>> {
+        match &self.last {
+            Some(last) if (self.same)(last, event) => self.repeated += 1,
+            _ => self.repeated = 1,
+        }
+        self.last = Some(event.clone());
+        if self.repeated < self.count {
+            return Some(Change::pass());
+        }
+        self.repeated = 0;
+        self.last = None;
+        None
+    }
+}
+
+impl<S, E: Clone, L: Layer<S, E>> Layer<S, E> for Debounce<L, E> {
+    forward_layer!(|this| this.layer => intercepted, passive_update, lifecycle, properties, on_remove_request);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{calls, CallKind, CallLog, RecordingLayer};
+    use crate::tests::{reports_error, reports_output, Reporter};
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec};
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Input {
+        Move(i32),
+        Click,
+    }
+
+    fn count(log: &CallLog<Input>, layer: &str, kind: CallKind) -> usize {
+        calls(log, Some(layer), kind).len()
+    }
+
+    #[test]
+    fn throttle() {
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(RecordingLayer::cloning("below", &log)),
+            Box::new(Throttle::new(RecordingLayer::cloning("passing", &log), 3)),
+        ]);
+        let mut swallowing = LayerManager::new(vec![
+            Box::new(RecordingLayer::cloning("below", &log)),
+            Box::new(Throttle::swallowing(
+                RecordingLayer::cloning("swallowing", &log),
+                4,
+            )),
+        ]);
+
+        for x in 0..10 {
+            manager.update(&mut (), Input::Move(x));
+        }
+        assert_eq!(count(&log, "passing", CallKind::Update), 4);
+        assert_eq!(count(&log, "below", CallKind::Update), 6);
+        assert_eq!(count(&log, "passing", CallKind::Passive), 10);
+        log.borrow_mut().clear();
+
+        for x in 0..10 {
+            swallowing.update(&mut (), Input::Move(x));
+        }
+        assert_eq!(count(&log, "swallowing", CallKind::Update), 3);
+        assert_eq!(count(&log, "below", CallKind::Update), 0);
+    }
+
+    #[test]
+    fn debounce() {
+        fn same(a: &Input, b: &Input) -> bool {
+            core::mem::discriminant(a) == core::mem::discriminant(b)
+        }
+
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![Box::new(Debounce::new(
+            RecordingLayer::cloning("debounced", &log),
+            3,
+            same,
+        ))]);
+
+        let burst = [
+            Input::Move(0),
+            Input::Move(1),
+            Input::Click,
+            Input::Move(2),
+            Input::Move(3),
+            Input::Move(4),
+            Input::Move(5),
+            Input::Click,
+            Input::Click,
+            Input::Click,
+        ];
+        for &input in &burst {
+            manager.update(&mut (), input);
+        }
+        assert_eq!(count(&log, "debounced", CallKind::Passive), 10);
+        let handled: vec::Vec<_> = calls(&log, Some("debounced"), CallKind::Update)
+            .iter()
+            .map(|call| call.event)
+            .collect();
+        assert_eq!(handled[..], [Input::Move(4), Input::Click]);
+    }
+
+    #[test]
+    fn forwarding() {
+        let mut throttled = Throttle::new(Reporter, 2);
+        assert!(reports_output(&mut throttled));
+        assert!(!reports_error(&mut throttled));
+        assert!(reports_error(&mut throttled));
+        assert!(!reports_output(&mut throttled));
+
+        fn same(_a: &crate::tests::Event, _b: &crate::tests::Event) -> bool {
+            true
+        }
+
+        let mut debounced = Debounce::new(Reporter, 2, same);
+        assert!(!reports_output(&mut debounced));
+        assert!(reports_output(&mut debounced));
+        assert!(!reports_error(&mut debounced));
+        assert!(reports_error(&mut debounced));
+    }
+}
@@ -0,0 +1,102 @@
+use super::Intercept;
+use crate::{Change, ChangeAction, Layer};
+
+/// A layer, which removes itself after handling a number of events.
+///
+/// Events passed by the wrapped layer don't count, so the layers below still receive them, and the layer is only removed together with an event it handled.
+pub struct TimeoutLayer<L> {
+    layer: L,
+    remaining: usize,
+}
+
+impl<L> TimeoutLayer<L> {
+    /// Wrap a layer, so it's removed after its `update` handled `count` events without passing them.
+    ///
+    /// Only events dispatched to this layer are counted. The layer can still remove itself earlier.
+    pub fn new(layer: L, count: usize) -> Self {
+        Self {
+            layer,
+            remaining: count,
+        }
+    }
+
+    /// The number of events this layer will still handle.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<S, E, L> Intercept<S, E> for TimeoutLayer<L> {
+    fn after(&mut self, change: Change<S, E>) -> Change<S, E> {
+        if change.action == ChangeAction::Pass {
+            return change;
+        }
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining == 0 {
+            change.removing()
+        } else {
+            change
+        }
+    }
+}
+
+impl<S, E, L: Layer<S, E>> Layer<S, E> for TimeoutLayer<L> {
+    forward_layer!(|this| this.layer => intercepted, passive_update, lifecycle, properties, on_remove_request);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{
+        reports_error, reports_output, Event, GlobalState, MainLayer, Reporter, TopLayer,
+    };
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec};
+
+    #[test]
+    fn timeout() {
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(TimeoutLayer::new(TopLayer, 2)),
+        ]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(manager.len(), 2);
+    }
+
+    #[test]
+    fn passed_events() {
+        let mut timeout = TimeoutLayer::new(TopLayer, 1);
+
+        let change = timeout.update(&mut GlobalState, &Event::Input);
+        assert_eq!(change.action, ChangeAction::Pass);
+        assert_eq!(timeout.remaining(), 1);
+        let change = timeout.update(&mut GlobalState, &Event::Idle);
+        assert_eq!(change.action, ChangeAction::Remove);
+    }
+
+    #[test]
+    fn early_removal() {
+        let mut timeout = TimeoutLayer::new(TopLayer, 5);
+
+        let change = timeout.update(&mut GlobalState, &Event::Idle);
+        assert_eq!(change.action, ChangeAction::None);
+        let change = timeout.update(&mut GlobalState, &Event::Exit);
+        assert_eq!(change.action, ChangeAction::Remove);
+        assert_eq!(timeout.remaining(), 3);
+    }
+
+    #[test]
+    fn forwarding() {
+        let mut timeout = TimeoutLayer::new(Reporter, 2);
+        assert!(reports_error(&mut timeout));
+        assert_eq!(timeout.remaining(), 2);
+        assert!(reports_output(&mut timeout));
+        assert_eq!(timeout.remaining(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{Change, Layer};
+
+/// A variant of `Layer` using associated types for the state and event, which avoids threading generics through helper code.
+///
+/// Wrap it using `into_layer` to use it where a `Layer` is expected.
+pub trait LayerT {
+    /// The global state.
+    type State;
+    /// The event type.
+    type Event;
+
+    /// Executed for all layers from bottom to top. Most useful for rendering.
+    fn passive_update(&mut self, _state: &mut Self::State, _event: &Self::Event) {}
+
+    /// Executed for top layer and optionally for more layers. Most useful for click events.
+    fn update(
+        &mut self,
+        state: &mut Self::State,
+        event: &Self::Event,
+    ) -> Change<Self::State, Self::Event>;
+
+    /// Wrap the layer, so it implements `Layer`.
+    fn into_layer(self) -> Typed<Self>
+    where
+        Self: Sized,
+    {
+        Typed(self)
+    }
+}
+
+/// A layer using associated types, which implements `Layer`. Created by `LayerT::into_layer`.
+pub struct Typed<L>(pub L);
+
+impl<L: LayerT> Layer<L::State, L::Event> for Typed<L> {
+    fn passive_update(&mut self, state: &mut L::State, event: &L::Event) {
+        self.0.passive_update(state, event)
+    }
+
+    fn update(&mut self, state: &mut L::State, event: &L::Event) -> Change<L::State, L::Event> {
+        self.0.update(state, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Event, GlobalState, MainLayer};
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec};
+
+    struct Top;
+
+    impl LayerT for Top {
+        type State = GlobalState;
+        type Event = Event;
+
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            match event {
+                Event::Input => Change::pass(),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_stack() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(Top.into_layer())]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+        manager.update(&mut state, Event::Exit);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 1);
+    }
+}
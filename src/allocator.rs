@@ -0,0 +1,66 @@
+//! The allocator of the stack of a layer manager.
+//!
+//! With the `allocator_api` feature, which requires a nightly compiler, the stack can be stored by any `Allocator`.
+//! Otherwise only the global allocator is available, and these types stand in for the unstable ones.
+
+#[cfg(feature = "allocator_api")]
+pub use alloc::alloc::{Allocator, Global};
+
+#[cfg(feature = "allocator_api")]
+pub(crate) type Stack<T, A> = alloc::vec::Vec<T, A>;
+
+#[cfg(not(feature = "allocator_api"))]
+pub use self::stable::{Allocator, Global};
+
+#[cfg(not(feature = "allocator_api"))]
+pub(crate) use self::stable::Stack;
+
+#[cfg(not(feature = "allocator_api"))]
+mod stable {
+    use alloc::vec::Vec;
+    use core::ops::{Deref, DerefMut};
+
+    mod sealed {
+        pub trait Sealed {}
+    }
+
+    /// An allocator for the stack of a layer manager.
+    ///
+    /// Without the `allocator_api` feature, only `Global` implements it.
+    pub trait Allocator: sealed::Sealed {}
+
+    /// The global allocator.
+    #[derive(Copy, Clone, Debug, Default)]
+    pub struct Global;
+
+    impl sealed::Sealed for Global {}
+
+    impl Allocator for Global {}
+
+    /// A vector, which keeps its allocator like `Vec<T, A>` does.
+    pub(crate) struct Stack<T, A>(Vec<T>, A);
+
+    impl<T, A> Stack<T, A> {
+        pub(crate) fn new_in(alloc: A) -> Self {
+            Self(Vec::new(), alloc)
+        }
+
+        pub(crate) fn allocator(&self) -> &A {
+            &self.1
+        }
+    }
+
+    impl<T, A> Deref for Stack<T, A> {
+        type Target = Vec<T>;
+
+        fn deref(&self) -> &Vec<T> {
+            &self.0
+        }
+    }
+
+    impl<T, A> DerefMut for Stack<T, A> {
+        fn deref_mut(&mut self) -> &mut Vec<T> {
+            &mut self.0
+        }
+    }
+}
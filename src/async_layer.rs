@@ -0,0 +1,269 @@
+//! Layers, which handle events asynchronously.
+//!
+//! This mirrors the synchronous layer system: An `AsyncLayerManager` awaits its layers one after another and applies their changes like the `LayerManager` does.
+//! Passive updates stay synchronous.
+
+use crate::{
+    simple::{self, Entry},
+    ChangeAction,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{future::Future, pin::Pin};
+
+/// The future returned by asynchronous layers.
+pub type LayerFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// The action, that will be done after handling an event by an asynchronous layer.
+pub struct AsyncChange<S, E> {
+    /// Add new layers on top of the current layer.
+    add: Vec<Box<dyn AsyncLayer<S, E>>>,
+    /// Special actions.
+    action: ChangeAction,
+}
+
+impl<S, E> AsyncChange<S, E> {
+    /// A simple change doing nothing.
+    pub fn none() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::None,
+        }
+    }
+
+    /// A change passing the event to the next layer.
+    pub fn pass() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Pass,
+        }
+    }
+
+    /// A change just adding new layers.
+    pub fn add(add: Vec<Box<dyn AsyncLayer<S, E>>>) -> Self {
+        Self {
+            add,
+            action: ChangeAction::None,
+        }
+    }
+
+    /// A simple change removing the current layer.
+    pub fn remove() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Remove,
+        }
+    }
+
+    /// A change replacing the current layer with new layers.
+    pub fn replace(add: Vec<Box<dyn AsyncLayer<S, E>>>) -> Self {
+        Self {
+            add,
+            action: ChangeAction::Remove,
+        }
+    }
+
+    /// A change removing all layers.
+    pub fn close() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Clear,
+        }
+    }
+
+    /// A change putting the current layer to sleep until it's woken up again.
+    pub fn sleep() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Sleep,
+        }
+    }
+
+    /// A change waking up all sleeping layers.
+    pub fn wake() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Wake,
+        }
+    }
+
+    /// A change replacing all layers with a new stack of layers.
+    pub fn clear(add: Vec<Box<dyn AsyncLayer<S, E>>>) -> Self {
+        Self {
+            add,
+            action: ChangeAction::Clear,
+        }
+    }
+}
+
+/// A trait, every asynchronous layer has to implement, in order to be used by the asynchronous layer manager.
+pub trait AsyncLayer<S, E> {
+    /// Executed for all layers from bottom to top. Most useful for rendering.
+    fn passive_update(&mut self, _state: &mut S, _event: &E) {}
+
+    /// Executed for top layer and optionally for more layers. The manager waits for the returned future before continuing.
+    fn update<'a>(
+        &'a mut self,
+        state: &'a mut S,
+        event: &'a E,
+    ) -> LayerFuture<'a, AsyncChange<S, E>>;
+}
+
+/// The layer manager for asynchronous layers.
+pub struct AsyncLayerManager<S, E> {
+    layers: Vec<Entry<dyn AsyncLayer<S, E>>>,
+}
+
+impl<S, E> AsyncLayerManager<S, E> {
+    /// Create a new layer manager containing specified initial layers.
+    pub fn new(layers: Vec<Box<dyn AsyncLayer<S, E>>>) -> Self {
+        Self {
+            layers: layers.into_iter().map(Entry::new).collect(),
+        }
+    }
+
+    /// The number of layers currently on the stack.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Checks if the stack contains no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Checks if the layer manger is still active.
+    pub fn is_active(&self) -> bool {
+        !self.layers.is_empty()
+    }
+
+    /// Handle an event by awaiting the layers from top to bottom until one of them doesn't pass it.
+    ///
+    /// Sleeping layers are skipped as if they passed the event.
+    /// Afterwards all layers are updated passively from bottom to top.
+    pub async fn update(&mut self, state: &mut S, event: E) {
+        let mut i = self.layers.len();
+        while i > 0 {
+            i -= 1;
+            let entry = &mut self.layers[i];
+            if entry.asleep {
+                continue;
+            }
+            let AsyncChange { add, action } = entry.layer.update(state, &event).await;
+            if simple::apply(&mut self.layers, i, add, action) {
+                break;
+            }
+        }
+
+        for entry in self.layers.iter_mut() {
+            entry.layer.passive_update(state, &event);
+        }
+    }
+}
+
+impl<S, E> Default for AsyncLayerManager<S, E> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use futures::executor::block_on;
+
+    pub enum Event {
+        Idle,
+        Input,
+        Exit,
+    }
+
+    pub struct GlobalState {
+        fetched: u32,
+    }
+
+    async fn fetch(state: &mut GlobalState) {
+        state.fetched += 1;
+    }
+
+    pub struct MainLayer;
+
+    impl AsyncLayer<GlobalState, Event> for MainLayer {
+        fn update<'a>(
+            &'a mut self,
+            state: &'a mut GlobalState,
+            event: &'a Event,
+        ) -> LayerFuture<'a, AsyncChange<GlobalState, Event>> {
+            Box::pin(async move {
+                match event {
+                    Event::Input => {
+                        fetch(state).await;
+                        AsyncChange::add(vec![Box::new(TopLayer)])
+                    }
+                    Event::Idle => AsyncChange::none(),
+                    Event::Exit => AsyncChange::remove(),
+                }
+            })
+        }
+    }
+
+    pub struct TopLayer;
+
+    impl AsyncLayer<GlobalState, Event> for TopLayer {
+        fn update<'a>(
+            &'a mut self,
+            _state: &'a mut GlobalState,
+            event: &'a Event,
+        ) -> LayerFuture<'a, AsyncChange<GlobalState, Event>> {
+            Box::pin(async move {
+                match event {
+                    Event::Input => AsyncChange::pass(),
+                    Event::Idle => AsyncChange::none(),
+                    Event::Exit => AsyncChange::remove(),
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn example() {
+        let mut manager = AsyncLayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState { fetched: 0 };
+
+        block_on(async {
+            manager.update(&mut state, Event::Idle).await;
+            manager.update(&mut state, Event::Input).await;
+            assert_eq!(manager.len(), 3);
+            manager.update(&mut state, Event::Idle).await;
+
+            while manager.is_active() {
+                manager.update(&mut state, Event::Exit).await;
+            }
+        });
+        assert_eq!(state.fetched, 1);
+    }
+
+    pub struct Navigator;
+
+    impl AsyncLayer<GlobalState, Event> for Navigator {
+        fn update<'a>(
+            &'a mut self,
+            _state: &'a mut GlobalState,
+            _event: &'a Event,
+        ) -> LayerFuture<'a, AsyncChange<GlobalState, Event>> {
+            Box::pin(async { AsyncChange::clear(vec![Box::new(MainLayer)]) })
+        }
+    }
+
+    #[test]
+    fn clear() {
+        let mut manager = AsyncLayerManager::new(vec![Box::new(TopLayer), Box::new(Navigator)]);
+        let mut state = GlobalState { fetched: 0 };
+
+        block_on(manager.update(&mut state, Event::Idle));
+        assert_eq!(manager.len(), 1);
+        block_on(manager.update(&mut state, Event::Input));
+        assert_eq!(manager.len(), 2);
+        assert_eq!(state.fetched, 1);
+    }
+}
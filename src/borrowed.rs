@@ -0,0 +1,277 @@
+//! Layers for events, which borrow data only for the duration of a single dispatch.
+//!
+//! A `LayerManager<S, E>` requires a single event type `E`, so events borrowing data would tie the whole manager to the lifetime of that data.
+//! Here the event type is chosen per dispatch by an `EventFamily`, so events can borrow short lived buffers without copying them.
+
+use crate::{
+    simple::{self, Entry},
+    ChangeAction,
+};
+use alloc::{boxed::Box, vec::Vec};
+
+/// A family of event types, which may borrow data for some lifetime.
+pub trait EventFamily {
+    /// The event type borrowing data for the lifetime `'a`.
+    type Event<'a>;
+}
+
+/// The action, that will be done after handling a borrowed event by a layer.
+pub struct BorrowedChange<S, F: EventFamily> {
+    /// Add new layers on top of the current layer.
+    add: Vec<Box<dyn BorrowedLayer<S, F>>>,
+    /// Special actions.
+    action: ChangeAction,
+}
+
+impl<S, F: EventFamily> BorrowedChange<S, F> {
+    /// A simple change doing nothing.
+    pub fn none() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::None,
+        }
+    }
+
+    /// A change passing the event to the next layer.
+    pub fn pass() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Pass,
+        }
+    }
+
+    /// A change just adding new layers.
+    pub fn add(add: Vec<Box<dyn BorrowedLayer<S, F>>>) -> Self {
+        Self {
+            add,
+            action: ChangeAction::None,
+        }
+    }
+
+    /// A simple change removing the current layer.
+    pub fn remove() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Remove,
+        }
+    }
+
+    /// A change replacing the current layer with new layers.
+    pub fn replace(add: Vec<Box<dyn BorrowedLayer<S, F>>>) -> Self {
+        Self {
+            add,
+            action: ChangeAction::Remove,
+        }
+    }
+
+    /// A change removing all layers.
+    pub fn close() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Clear,
+        }
+    }
+
+    /// A change putting the current layer to sleep until it's woken up again.
+    pub fn sleep() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Sleep,
+        }
+    }
+
+    /// A change waking up all sleeping layers.
+    pub fn wake() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Wake,
+        }
+    }
+
+    /// A change replacing all layers with a new stack of layers.
+    pub fn clear(add: Vec<Box<dyn BorrowedLayer<S, F>>>) -> Self {
+        Self {
+            add,
+            action: ChangeAction::Clear,
+        }
+    }
+}
+
+/// A trait, every layer handling borrowed events has to implement, in order to be used by the borrowed layer manager.
+pub trait BorrowedLayer<S, F: EventFamily> {
+    /// Executed for all layers from bottom to top. Most useful for rendering.
+    fn passive_update(&mut self, _state: &mut S, _event: &F::Event<'_>) {}
+
+    /// Executed for top layer and optionally for more layers. Most useful for click events.
+    fn update(&mut self, state: &mut S, event: &F::Event<'_>) -> BorrowedChange<S, F>;
+}
+
+/// The layer manager for layers handling borrowed events.
+pub struct BorrowedLayerManager<S, F: EventFamily> {
+    layers: Vec<Entry<dyn BorrowedLayer<S, F>>>,
+}
+
+impl<S, F: EventFamily> BorrowedLayerManager<S, F> {
+    /// Create a new layer manager containing specified initial layers.
+    pub fn new(layers: Vec<Box<dyn BorrowedLayer<S, F>>>) -> Self {
+        Self {
+            layers: layers.into_iter().map(Entry::new).collect(),
+        }
+    }
+
+    /// The number of layers currently on the stack.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Checks if the stack contains no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Checks if the layer manger is still active.
+    pub fn is_active(&self) -> bool {
+        !self.layers.is_empty()
+    }
+
+    /// Handle an event, which may borrow data for the duration of this call.
+    ///
+    /// Layers are asked from top to bottom until one of them doesn't pass the event.
+    /// Sleeping layers are skipped as if they passed the event.
+    /// Afterwards all layers are updated passively from bottom to top.
+    pub fn update(&mut self, state: &mut S, event: &F::Event<'_>) {
+        let mut i = self.layers.len();
+        while i > 0 {
+            i -= 1;
+            let entry = &mut self.layers[i];
+            if entry.asleep {
+                continue;
+            }
+            let BorrowedChange { add, action } = entry.layer.update(state, event);
+            if simple::apply(&mut self.layers, i, add, action) {
+                break;
+            }
+        }
+
+        for entry in self.layers.iter_mut() {
+            entry.layer.passive_update(state, event);
+        }
+    }
+}
+
+impl<S, F: EventFamily> Default for BorrowedLayerManager<S, F> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    pub enum Frame<'a> {
+        Data(&'a [u8]),
+        Close,
+    }
+
+    pub struct Frames;
+
+    impl EventFamily for Frames {
+        type Event<'a> = Frame<'a>;
+    }
+
+    #[derive(Default)]
+    pub struct Received {
+        sum: u32,
+        payloads: Vec<*const u8>,
+        draws: u32,
+    }
+
+    pub struct Decoder;
+
+    impl BorrowedLayer<Received, Frames> for Decoder {
+        fn passive_update(&mut self, state: &mut Received, _event: &Frame) {
+            state.draws += 1;
+        }
+
+        fn update(
+            &mut self,
+            state: &mut Received,
+            event: &Frame,
+        ) -> BorrowedChange<Received, Frames> {
+            match event {
+                Frame::Data(payload) => {
+                    state.sum += payload.iter().map(|&byte| u32::from(byte)).sum::<u32>();
+                    state.payloads.push(payload.as_ptr());
+                    BorrowedChange::none()
+                }
+                Frame::Close => BorrowedChange::remove(),
+            }
+        }
+    }
+
+    pub struct Filter;
+
+    impl BorrowedLayer<Received, Frames> for Filter {
+        fn update(
+            &mut self,
+            state: &mut Received,
+            event: &Frame,
+        ) -> BorrowedChange<Received, Frames> {
+            match event {
+                Frame::Data(payload) => {
+                    state.payloads.push(payload.as_ptr());
+                    BorrowedChange::pass()
+                }
+                Frame::Close => BorrowedChange::remove(),
+            }
+        }
+    }
+
+    #[test]
+    fn borrowed_frames() {
+        let mut manager: BorrowedLayerManager<Received, Frames> =
+            BorrowedLayerManager::new(vec![Box::new(Decoder), Box::new(Filter)]);
+        let mut state = Received::default();
+
+        for round in 0..3u8 {
+            let buffer = vec![round; 4];
+            manager.update(&mut state, &Frame::Data(&buffer));
+            assert_eq!(state.payloads[..], [buffer.as_ptr(), buffer.as_ptr()]);
+            state.payloads.clear();
+        }
+        assert_eq!(state.sum, 12);
+        assert_eq!(state.draws, 3);
+
+        while manager.is_active() {
+            manager.update(&mut state, &Frame::Close);
+        }
+    }
+
+    pub struct Restart;
+
+    impl BorrowedLayer<Received, Frames> for Restart {
+        fn update(
+            &mut self,
+            _state: &mut Received,
+            _event: &Frame,
+        ) -> BorrowedChange<Received, Frames> {
+            BorrowedChange::clear(vec![Box::new(Decoder)])
+        }
+    }
+
+    #[test]
+    fn clear() {
+        let mut manager: BorrowedLayerManager<Received, Frames> =
+            BorrowedLayerManager::new(vec![Box::new(Filter), Box::new(Restart)]);
+        let mut state = Received::default();
+
+        let buffer = [1, 2];
+        manager.update(&mut state, &Frame::Data(&buffer));
+        assert_eq!(manager.len(), 1);
+        manager.update(&mut state, &Frame::Data(&buffer));
+        assert_eq!(state.sum, 3);
+        assert_eq!(state.draws, 2);
+    }
+}
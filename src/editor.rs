@@ -0,0 +1,70 @@
+use crate::{Allocator, Edit, Global, Layer, LayerManager};
+use alloc::boxed::Box;
+
+/// Edits the stack of a layer manager during `LayerManager::transaction`.
+///
+/// Hooks for the top layer are only called after the transaction. The other operations behave like the methods of the manager with the same name.
+pub struct StackEditor<'a, S, E, A: Allocator = Global> {
+    manager: &'a mut LayerManager<S, E, A>,
+}
+
+impl<'a, S, E, A: Allocator> StackEditor<'a, S, E, A> {
+    pub(crate) fn new(manager: &'a mut LayerManager<S, E, A>) -> Self {
+        Self { manager }
+    }
+
+    /// The number of layers on the stack.
+    pub fn len(&self) -> usize {
+        self.manager.len()
+    }
+
+    /// Checks if the stack contains no layers.
+    pub fn is_empty(&self) -> bool {
+        self.manager.is_empty()
+    }
+
+    /// Add a layer on top of the stack.
+    pub fn push(&mut self, layer: Box<dyn Layer<S, E>>) {
+        self.manager.push(layer);
+    }
+
+    /// Insert a layer at `index`, moving all layers above it up.
+    ///
+    /// Panics if `index` is greater than the number of layers.
+    pub fn insert_at(&mut self, index: usize, layer: Box<dyn Layer<S, E>>) {
+        self.manager.insert_at(index, layer);
+    }
+
+    /// Remove the top layer and return it, unless it's sticky.
+    ///
+    /// There's no state during a transaction, so `Layer::on_remove_request` isn't consulted.
+    pub fn pop(&mut self) -> Option<Box<dyn Layer<S, E>>> {
+        let index = self.manager.active_index()?;
+        self.remove_at(index)
+    }
+
+    /// Remove the layer at `index` and return it, unless it's sticky.
+    ///
+    /// There's no state during a transaction, so `Layer::on_remove_request` isn't consulted.
+    pub fn remove_at(&mut self, index: usize) -> Option<Box<dyn Layer<S, E>>> {
+        if self.manager.layers.get(index)?.layer.sticky() {
+            return None;
+        }
+        self.manager.lose_history();
+        Some(self.manager.remove_layer(index))
+    }
+
+    /// Swap the layers at `a` and `b`.
+    ///
+    /// Panics if any of the indices is out of bounds.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.manager.layers.swap(a, b);
+        self.manager.record(Edit::Swapped(a, b));
+        self.manager.dirty = true;
+    }
+
+    /// Remove all layers above the first `len` layers from top to bottom.
+    pub fn truncate(&mut self, len: usize) {
+        self.manager.truncate(len);
+    }
+}
@@ -0,0 +1,241 @@
+/// Implement methods of `Layer` by forwarding them to an inner layer.
+///
+/// Without arguments, everything is forwarded to the dereferenced layer, which is used for boxes and references.
+/// Otherwise the inner layer is described by an expression using `this` for `self`, optionally followed by a second expression used in methods taking `self` mutably.
+/// After the arrow follow the forwarded methods and groups of methods:
+///
+/// - `dispatch`: `passive_update`, `update`, `update_output`, `try_update` and `take_event`.
+/// - `intercepted`: `update`, `update_output`, `try_update` and `take_event`, changed by the `Intercept` implementation of the layer.
+/// - `lifecycle`: `on_attach`, `on_detach`, `on_cover` and `on_uncover`.
+/// - `properties`: `priority`, `tags`, `z_order`, `needs_passive`, `enabled`, `set_enabled`, `sticky`, `is_persistent`, `is_opaque`, `name`, `layer_type_id` and `save`.
+///
+/// New methods of `Layer` should be added to one of the groups, so every adapter forwards them.
+macro_rules! forward_layer {
+    () => {
+        forward_layer!(|this| (**this) => dispatch, lifecycle, properties, on_remove_request, try_clone);
+    };
+    (|$this:ident| $shared:expr, $mutable:expr => $($method:ident),* $(,)?) => {
+        $(forward_layer!(@$method $this $shared, $mutable);)*
+    };
+    (|$this:ident| $inner:expr => $($method:ident),* $(,)?) => {
+        $(forward_layer!(@$method $this $inner, $inner);)*
+    };
+
+    (@dispatch $this:ident $shared:expr, $mutable:expr) => {
+        forward_layer!(|$this| $shared, $mutable => passive_update, update, update_output, try_update, take_event);
+    };
+    (@lifecycle $this:ident $shared:expr, $mutable:expr) => {
+        forward_layer!(|$this| $shared, $mutable => on_attach, on_detach, on_cover, on_uncover);
+    };
+    (@properties $this:ident $shared:expr, $mutable:expr) => {
+        forward_layer!(|$this| $shared, $mutable =>
+            priority, tags, z_order, needs_passive, enabled, set_enabled,
+            sticky, is_persistent, is_opaque, name, layer_type_id, save,
+        );
+    };
+
+    (@intercepted $this:ident $shared:expr, $mutable:expr) => {
+        fn update(&mut self, state: &mut S, event: &E) -> $crate::Change<S, E> {
+            let $this = self;
+            if let Some(change) = $crate::adapters::Intercept::before(&mut *$this, state, event) {
+                return change;
+            }
+            let change = $mutable.update(state, event);
+            $crate::adapters::Intercept::after($this, change)
+        }
+
+        fn update_output(
+            &mut self,
+            state: &mut S,
+            event: &E,
+        ) -> (
+            $crate::Change<S, E>,
+            Option<alloc::boxed::Box<dyn core::any::Any>>,
+        ) {
+            let $this = self;
+            if let Some(change) = $crate::adapters::Intercept::before(&mut *$this, state, event) {
+                return (change, None);
+            }
+            let (change, output) = $mutable.update_output(state, event);
+            ($crate::adapters::Intercept::after($this, change), output)
+        }
+
+        fn try_update(
+            &mut self,
+            state: &mut S,
+            event: &E,
+        ) -> Result<$crate::Change<S, E>, $crate::LayerError> {
+            let $this = self;
+            if let Some(change) = $crate::adapters::Intercept::before(&mut *$this, state, event) {
+                return Ok(change);
+            }
+            let change = $mutable.try_update(state, event)?;
+            Ok($crate::adapters::Intercept::after($this, change))
+        }
+
+        fn take_event(&mut self, state: &mut S, event: E) -> Result<$crate::Change<S, E>, E> {
+            let $this = self;
+            match $crate::adapters::Intercept::before(&mut *$this, state, &event) {
+                Some(change) if change.action == $crate::ChangeAction::Pass => return Err(event),
+                Some(change) => return Ok(change),
+                None => (),
+            }
+            let change = $mutable.take_event(state, event)?;
+            Ok($crate::adapters::Intercept::after($this, change))
+        }
+    };
+
+    (@passive_update $this:ident $shared:expr, $mutable:expr) => {
+        fn passive_update(&mut self, state: &mut S, event: &E) {
+            let $this = self;
+            $mutable.passive_update(state, event)
+        }
+    };
+    (@update $this:ident $shared:expr, $mutable:expr) => {
+        fn update(&mut self, state: &mut S, event: &E) -> $crate::Change<S, E> {
+            let $this = self;
+            $mutable.update(state, event)
+        }
+    };
+    (@update_output $this:ident $shared:expr, $mutable:expr) => {
+        fn update_output(
+            &mut self,
+            state: &mut S,
+            event: &E,
+        ) -> (
+            $crate::Change<S, E>,
+            Option<alloc::boxed::Box<dyn core::any::Any>>,
+        ) {
+            let $this = self;
+            $mutable.update_output(state, event)
+        }
+    };
+    (@try_update $this:ident $shared:expr, $mutable:expr) => {
+        fn try_update(
+            &mut self,
+            state: &mut S,
+            event: &E,
+        ) -> Result<$crate::Change<S, E>, $crate::LayerError> {
+            let $this = self;
+            $mutable.try_update(state, event)
+        }
+    };
+    (@take_event $this:ident $shared:expr, $mutable:expr) => {
+        fn take_event(&mut self, state: &mut S, event: E) -> Result<$crate::Change<S, E>, E> {
+            let $this = self;
+            $mutable.take_event(state, event)
+        }
+    };
+    (@on_attach $this:ident $shared:expr, $mutable:expr) => {
+        fn on_attach(&mut self) {
+            let $this = self;
+            $mutable.on_attach()
+        }
+    };
+    (@on_detach $this:ident $shared:expr, $mutable:expr) => {
+        fn on_detach(&mut self) {
+            let $this = self;
+            $mutable.on_detach()
+        }
+    };
+    (@on_cover $this:ident $shared:expr, $mutable:expr) => {
+        fn on_cover(&mut self) {
+            let $this = self;
+            $mutable.on_cover()
+        }
+    };
+    (@on_uncover $this:ident $shared:expr, $mutable:expr) => {
+        fn on_uncover(&mut self) {
+            let $this = self;
+            $mutable.on_uncover()
+        }
+    };
+    (@priority $this:ident $shared:expr, $mutable:expr) => {
+        fn priority(&self) -> i32 {
+            let $this = self;
+            $shared.priority()
+        }
+    };
+    (@tags $this:ident $shared:expr, $mutable:expr) => {
+        fn tags(&self) -> u32 {
+            let $this = self;
+            $shared.tags()
+        }
+    };
+    (@z_order $this:ident $shared:expr, $mutable:expr) => {
+        fn z_order(&self) -> i32 {
+            let $this = self;
+            $shared.z_order()
+        }
+    };
+    (@needs_passive $this:ident $shared:expr, $mutable:expr) => {
+        fn needs_passive(&self) -> bool {
+            let $this = self;
+            $shared.needs_passive()
+        }
+    };
+    (@enabled $this:ident $shared:expr, $mutable:expr) => {
+        fn enabled(&self) -> bool {
+            let $this = self;
+            $shared.enabled()
+        }
+    };
+    (@set_enabled $this:ident $shared:expr, $mutable:expr) => {
+        fn set_enabled(&mut self, enabled: bool) {
+            let $this = self;
+            $mutable.set_enabled(enabled)
+        }
+    };
+    (@sticky $this:ident $shared:expr, $mutable:expr) => {
+        fn sticky(&self) -> bool {
+            let $this = self;
+            $shared.sticky()
+        }
+    };
+    (@is_persistent $this:ident $shared:expr, $mutable:expr) => {
+        fn is_persistent(&self) -> bool {
+            let $this = self;
+            $shared.is_persistent()
+        }
+    };
+    (@is_opaque $this:ident $shared:expr, $mutable:expr) => {
+        fn is_opaque(&self) -> bool {
+            let $this = self;
+            $shared.is_opaque()
+        }
+    };
+    (@on_remove_request $this:ident $shared:expr, $mutable:expr) => {
+        fn on_remove_request(&mut self, state: &mut S) -> bool {
+            let $this = self;
+            $mutable.on_remove_request(state)
+        }
+    };
+    (@try_clone $this:ident $shared:expr, $mutable:expr) => {
+        fn try_clone(&self) -> Option<alloc::boxed::Box<dyn $crate::Layer<S, E>>> {
+            let $this = self;
+            $shared.try_clone()
+        }
+    };
+    (@name $this:ident $shared:expr, $mutable:expr) => {
+        fn name(&self) -> &'static str {
+            let $this = self;
+            $shared.name()
+        }
+    };
+    (@layer_type_id $this:ident $shared:expr, $mutable:expr) => {
+        fn layer_type_id(&self) -> core::any::TypeId
+        where
+            Self: 'static,
+        {
+            let $this = self;
+            $shared.layer_type_id()
+        }
+    };
+    (@save $this:ident $shared:expr, $mutable:expr) => {
+        #[cfg(feature = "serde")]
+        fn save(&self) -> Option<alloc::vec::Vec<u8>> {
+            let $this = self;
+            $shared.save()
+        }
+    };
+}
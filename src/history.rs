@@ -0,0 +1,141 @@
+use crate::{Allocator, Entry, Layer, LayerManager};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+
+pub(crate) enum Edit<S, E> {
+    Inserted {
+        index: usize,
+        count: usize,
+    },
+    Removed {
+        index: usize,
+        layers: Vec<Box<dyn Layer<S, E>>>,
+    },
+    Swapped(usize, usize),
+}
+
+pub(crate) struct History<S, E> {
+    max_entries: usize,
+    current: Vec<Edit<S, E>>,
+    undo: VecDeque<Vec<Edit<S, E>>>,
+    redo: Vec<Vec<Edit<S, E>>>,
+}
+
+impl<S, E, A: Allocator> LayerManager<S, E, A> {
+    /// Start recording the changes of the stack, so they can be reverted by `undo`, discarding any previous history.
+    ///
+    /// Every update and every method changing the stack adds a single entry to the history, and only the latest `max_entries` entries are kept.
+    /// The history keeps the removed layers alive, until their entries are dropped.
+    /// Returning removed layers to the caller, like `pop`, `splice` or `update_taking_removed` do, clears the history, since these layers can't be restored anymore.
+    pub fn enable_history(&mut self, max_entries: usize) {
+        self.history = Some(History {
+            max_entries,
+            current: Vec::new(),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        });
+    }
+
+    /// Stop recording the changes of the stack and drop the history.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Revert the latest entry of the history, and return if there was an entry to revert.
+    ///
+    /// Reverted removals attach the layers again, and reverted insertions detach them. Restored layers get new ids and are awake.
+    pub fn undo(&mut self) -> bool {
+        self.walk_history(
+            |history| history.undo.pop_back(),
+            |history, step| history.redo.push(step),
+        )
+    }
+
+    /// Apply the latest entry reverted by `undo` again, and return if there was an entry to apply.
+    ///
+    /// Any other change of the stack discards the reverted entries.
+    pub fn redo(&mut self) -> bool {
+        self.walk_history(
+            |history| history.redo.pop(),
+            |history, step| history.undo.push_back(step),
+        )
+    }
+
+    pub(crate) fn record(&mut self, edit: Edit<S, E>) {
+        if let Some(history) = &mut self.history {
+            history.current.push(edit);
+        }
+    }
+
+    pub(crate) fn lose_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.current.clear();
+            history.undo.clear();
+            history.redo.clear();
+        }
+    }
+
+    pub(crate) fn commit_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            if history.current.is_empty() {
+                return;
+            }
+            history
+                .undo
+                .push_back(core::mem::take(&mut history.current));
+            if history.undo.len() > history.max_entries {
+                history.undo.pop_front();
+            }
+            history.redo.clear();
+        }
+    }
+
+    fn walk_history(
+        &mut self,
+        take: impl FnOnce(&mut History<S, E>) -> Option<Vec<Edit<S, E>>>,
+        keep: impl FnOnce(&mut History<S, E>, Vec<Edit<S, E>>),
+    ) -> bool {
+        let mut history = match self.history.take() {
+            Some(history) => history,
+            None => return false,
+        };
+        let walked = match take(&mut history) {
+            Some(step) => {
+                let inverse = self.revert(step);
+                keep(&mut history, inverse);
+                true
+            }
+            None => false,
+        };
+        self.history = Some(history);
+        self.refresh_cover();
+        walked
+    }
+
+    fn revert(&mut self, step: Vec<Edit<S, E>>) -> Vec<Edit<S, E>> {
+        let mut inverse = Vec::with_capacity(step.len());
+        for edit in step.into_iter().rev() {
+            inverse.push(match edit {
+                Edit::Inserted { index, count } => Edit::Removed {
+                    index,
+                    layers: (0..count).map(|_| self.remove_layer(index)).collect(),
+                },
+                Edit::Removed { index, layers } => {
+                    let count = layers.len();
+                    for (offset, layer) in layers.into_iter().enumerate() {
+                        let id = self.next_id();
+                        self.layers.insert(index + offset, Entry::new(layer, id));
+                    }
+                    self.notify_added(index..index + count);
+                    self.dirty = true;
+                    Edit::Inserted { index, count }
+                }
+                Edit::Swapped(a, b) => {
+                    self.layers.swap(a, b);
+                    self.dirty = true;
+                    Edit::Swapped(a, b)
+                }
+            });
+        }
+        inverse
+    }
+}
@@ -0,0 +1,272 @@
+/// Define an enum of layers, which implements `Layer` by delegating to the contained layer.
+///
+/// This allows storing different kinds of layers as a single concrete type.
+/// `From` is implemented for each of the contained layer types.
+///
+/// ```
+/// use layer_system::{layer_enum, Change, Layer, LayerManager};
+///
+/// struct Main;
+///
+/// impl Layer<(), ()> for Main {
+///     fn update(&mut self, _state: &mut (), _event: &()) -> Change<(), ()> {
+///         Change::none()
+///     }
+/// }
+///
+/// struct Top;
+///
+/// impl Layer<(), ()> for Top {
+///     fn update(&mut self, _state: &mut (), _event: &()) -> Change<(), ()> {
+///         Change::remove()
+///     }
+/// }
+///
+/// layer_enum! {
+///     enum AppLayer<(), ()> {
+///         Main(Main),
+///         Top(Top),
+///     }
+/// }
+///
+/// let mut manager = LayerManager::new(vec![
+///     Box::new(AppLayer::from(Main)),
+///     Box::new(AppLayer::from(Top)),
+/// ]);
+/// manager.update(&mut (), ());
+/// assert_eq!(manager.len(), 1);
+/// ```
+#[macro_export]
+macro_rules! layer_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident<$state:ty, $event:ty> {
+            $($variant:ident($layer:ty)),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                #[allow(missing_docs)]
+                $variant($layer),
+            )*
+        }
+
+        impl $crate::Layer<$state, $event> for $name {
+            fn passive_update(&mut self, state: &mut $state, event: &$event) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::passive_update(layer, state, event),)*
+                }
+            }
+
+            fn update(&mut self, state: &mut $state, event: &$event) -> $crate::Change<$state, $event> {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::update(layer, state, event),)*
+                }
+            }
+
+            fn update_output(
+                &mut self,
+                state: &mut $state,
+                event: &$event,
+            ) -> (
+                $crate::Change<$state, $event>,
+                ::core::option::Option<$crate::__private::Box<dyn ::core::any::Any>>,
+            ) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::update_output(layer, state, event),)*
+                }
+            }
+
+            fn on_attach(&mut self) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::on_attach(layer),)*
+                }
+            }
+
+            fn on_detach(&mut self) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::on_detach(layer),)*
+                }
+            }
+
+            fn on_cover(&mut self) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::on_cover(layer),)*
+                }
+            }
+
+            fn on_uncover(&mut self) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::on_uncover(layer),)*
+                }
+            }
+
+            fn priority(&self) -> i32 {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::priority(layer),)*
+                }
+            }
+
+            fn tags(&self) -> u32 {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::tags(layer),)*
+                }
+            }
+
+            fn z_order(&self) -> i32 {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::z_order(layer),)*
+                }
+            }
+
+            fn needs_passive(&self) -> bool {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::needs_passive(layer),)*
+                }
+            }
+
+            fn enabled(&self) -> bool {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::enabled(layer),)*
+                }
+            }
+
+            fn set_enabled(&mut self, enabled: bool) {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::set_enabled(layer, enabled),)*
+                }
+            }
+
+            fn sticky(&self) -> bool {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::sticky(layer),)*
+                }
+            }
+
+            fn is_persistent(&self) -> bool {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::is_persistent(layer),)*
+                }
+            }
+
+            fn is_opaque(&self) -> bool {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::is_opaque(layer),)*
+                }
+            }
+
+            fn on_remove_request(&mut self, state: &mut $state) -> bool {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::on_remove_request(layer, state),)*
+                }
+            }
+
+            fn try_update(
+                &mut self,
+                state: &mut $state,
+                event: &$event,
+            ) -> ::core::result::Result<$crate::Change<$state, $event>, $crate::LayerError> {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::try_update(layer, state, event),)*
+                }
+            }
+
+            fn take_event(
+                &mut self,
+                state: &mut $state,
+                event: $event,
+            ) -> ::core::result::Result<$crate::Change<$state, $event>, $event> {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::take_event(layer, state, event),)*
+                }
+            }
+
+            fn try_clone(
+                &self,
+            ) -> ::core::option::Option<$crate::__private::Box<dyn $crate::Layer<$state, $event>>> {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::try_clone(layer),)*
+                }
+            }
+
+            fn name(&self) -> &'static str {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::name(layer),)*
+                }
+            }
+
+            fn layer_type_id(&self) -> ::core::any::TypeId
+            where
+                Self: 'static,
+            {
+                match self {
+                    $($name::$variant(layer) => $crate::Layer::<$state, $event>::layer_type_id(layer),)*
+                }
+            }
+
+            $crate::__layer_enum_save!($name, $state, $event, $($variant)*);
+        }
+
+        $(
+            impl ::core::convert::From<$layer> for $name {
+                fn from(layer: $layer) -> Self {
+                    $name::$variant(layer)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __layer_enum_save {
+    ($name:ident, $state:ty, $event:ty, $($variant:ident)*) => {
+        fn save(&self) -> ::core::option::Option<$crate::__private::Vec<u8>> {
+            match self {
+                $($name::$variant(layer) => $crate::Layer::<$state, $event>::save(layer),)*
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __layer_enum_save {
+    ($($input:tt)*) => {};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{Event, GlobalState, MainLayer, TopLayer};
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec};
+
+    layer_enum! {
+        /// The layers of the example.
+        pub enum AppLayer<GlobalState, Event> {
+            Main(MainLayer),
+            Top(TopLayer),
+        }
+    }
+
+    #[test]
+    fn example() {
+        let mut manager = LayerManager::new(vec![
+            Box::new(AppLayer::from(MainLayer)),
+            Box::new(AppLayer::from(TopLayer)),
+        ]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+        manager.update(&mut state, Event::Idle);
+
+        while manager.is_active() {
+            manager.update(&mut state, Event::Exit);
+        }
+    }
+}
@@ -1,11 +1,61 @@
 #![deny(missing_docs)]
 #![no_std]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 /*!
 See [README.md]
 **/
-use arrayvec::ArrayVec;
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    any::{Any, TypeId},
+    fmt,
+    ops::Range,
+};
+
+#[cfg(feature = "serde")]
+pub use serde;
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::debug!($($arg)*);
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{boxed::Box, vec::Vec};
+}
+
+#[macro_use]
+mod forward;
+#[macro_use]
+mod layer_enum;
+
+pub mod adapters;
+pub use adapters::{LayerExt, LayerT};
+mod allocator;
+use allocator::Stack;
+pub use allocator::{Allocator, Global};
+#[cfg(feature = "async")]
+pub mod async_layer;
+pub mod borrowed;
+mod editor;
+pub use editor::StackEditor;
+mod history;
+mod simple;
+use history::{Edit, History};
+#[cfg(feature = "std")]
+pub mod mailbox;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+mod tuple;
 
 /// A special action for the layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ChangeAction {
     /// No special action to the layer.
     None,
@@ -15,72 +65,287 @@ pub enum ChangeAction {
     Remove,
     /// Remove all layers.
     Clear,
+    /// Put the current layer to sleep. Sleeping layers are skipped by `update` but still receive `passive_update`.
+    Sleep,
+    /// Wake up all sleeping layers.
+    Wake,
 }
 
 /// The action, that will be done after handling an event by a layer.
+///
+/// Changes always use the global allocator, also for managers created by `LayerManager::new_in`. The added layers are moved into the stack of the manager.
 pub struct Change<S, E> {
     /// Add new layers on top of the current layer.
-    add: ArrayVec<[LayerBox<S, E>; 10]>,
+    add: Vec<Box<dyn Layer<S, E>>>,
     /// Special actions.
     action: ChangeAction,
+    /// Where the new layers are added.
+    placement: Placement,
+    /// Request a redraw, even if the stack doesn't change.
+    dirty: bool,
+}
+
+#[derive(Copy, Clone)]
+enum Placement {
+    Above,
+    Bottom,
+    Dispatched,
+    Offset(isize),
 }
 
 impl<S, E> Change<S, E> {
     /// A simple change doing nothing.
     pub fn none() -> Self {
         Self {
-            add: ArrayVec::new(),
+            add: Vec::new(),
             action: ChangeAction::None,
+            placement: Placement::Above,
+            dirty: false,
         }
     }
 
     /// A change passing the event to the next layer.
     pub fn pass() -> Self {
         Self {
-            add: ArrayVec::new(),
+            add: Vec::new(),
             action: ChangeAction::Pass,
+            placement: Placement::Above,
+            dirty: false,
         }
     }
 
-    /// A change just adding new layers.
-    pub fn add(add: ArrayVec<[LayerBox<S, E>; 10]>) -> Self {
+    /// A change just adding new layers directly above the current layer.
+    ///
+    /// The added layers keep their order, so the first layer ends up lowest and the last layer ends up highest.
+    pub fn add(add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>) -> Self {
         Self {
-            add,
+            add: add.into_iter().collect(),
             action: ChangeAction::None,
+            placement: Placement::Above,
+            dirty: false,
+        }
+    }
+
+    /// A change adding a single new layer.
+    pub fn add_one(layer: impl Layer<S, E> + 'static) -> Self {
+        Self::add([Box::new(layer) as Box<dyn Layer<S, E>>])
+    }
+
+    /// A change adding the layer if there is one, and otherwise doing nothing like `none`.
+    pub fn add_maybe(layer: Option<Box<dyn Layer<S, E>>>) -> Self {
+        Self::add(layer)
+    }
+
+    /// A change adding the layer if `cond` is true, and otherwise doing nothing like `none`.
+    ///
+    /// The layer is always created, and dropped if it isn't added. Use `add_maybe` to only create it when needed.
+    pub fn push_if(cond: bool, layer: impl Layer<S, E> + 'static) -> Self {
+        if cond {
+            Self::add_one(layer)
+        } else {
+            Self::none()
         }
     }
 
     /// A simple change removing the current layer.
     pub fn remove() -> Self {
         Self {
-            add: ArrayVec::new(),
+            add: Vec::new(),
             action: ChangeAction::Remove,
+            placement: Placement::Above,
+            dirty: false,
         }
     }
 
     /// A change replacing the current layer with new layers.
-    pub fn replace(add: ArrayVec<[LayerBox<S, E>; 10]>) -> Self {
+    ///
+    /// The added layers take the place of the current layer in their order, the first layer ending up lowest.
+    pub fn replace(add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>) -> Self {
         Self {
-            add,
+            add: add.into_iter().collect(),
             action: ChangeAction::Remove,
+            placement: Placement::Above,
+            dirty: false,
         }
     }
 
+    /// A change replacing the current layer with a single new layer.
+    pub fn replace_one(layer: impl Layer<S, E> + 'static) -> Self {
+        Self::replace([Box::new(layer) as Box<dyn Layer<S, E>>])
+    }
+
     /// A change removing all layers.
     pub fn close() -> Self {
         Self {
-            add: ArrayVec::new(),
+            add: Vec::new(),
             action: ChangeAction::Clear,
+            placement: Placement::Above,
+            dirty: false,
+        }
+    }
+
+    /// A change putting the current layer to sleep until it's woken up again.
+    pub fn sleep() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Sleep,
+            placement: Placement::Above,
+            dirty: false,
+        }
+    }
+
+    /// A change waking up all sleeping layers.
+    pub fn wake() -> Self {
+        Self {
+            add: Vec::new(),
+            action: ChangeAction::Wake,
+            placement: Placement::Above,
+            dirty: false,
         }
     }
 
     /// A change replacing all layers with a new stack of layers.
-    pub fn clear(add: ArrayVec<[LayerBox<S, E>; 10]>) -> Self {
+    ///
+    /// The layers are specified from bottom to top. All layers are removed before the new layers are added, so `on_detach` is called for the old layers first.
+    /// Sticky and persistent layers stay below the new layers in their original order.
+    pub fn clear(add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>) -> Self {
         Self {
-            add,
+            add: add.into_iter().collect(),
             action: ChangeAction::Clear,
+            placement: Placement::Above,
+            dirty: false,
+        }
+    }
+
+    /// A change replacing all layers with a single new root layer, for example when navigating to a new screen and discarding the history.
+    pub fn reset(root: Box<dyn Layer<S, E>>) -> Self {
+        Self::clear([root])
+    }
+
+    /// A change adding new layers on top of the current layer, which also receive the event being dispatched, from top to bottom, like in `LayerManager::update`.
+    ///
+    /// Layers added this way by the newly added layers receive the event as well, up to `LayerManager::MAX_REDISPATCH` times per update.
+    /// This only applies to the change of the layer consuming the event.
+    pub fn add_and_dispatch(add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>) -> Self {
+        Self {
+            add: add.into_iter().collect(),
+            action: ChangeAction::None,
+            placement: Placement::Dispatched,
+            dirty: false,
+        }
+    }
+
+    /// A change replacing the current layer with new layers, which also receive the event being dispatched, like `Change::add_and_dispatch`.
+    pub fn replace_and_dispatch(add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>) -> Self {
+        Self::add_and_dispatch(add).removing()
+    }
+
+    /// A change adding new layers at the bottom of the stack, below all existing layers.
+    ///
+    /// The indices of all existing layers increase by the number of added layers.
+    /// Normally only one layer changes the stack per update, but passing layers can also add layers at the bottom. In this case, the layers added later end up below the layers added earlier.
+    pub fn add_bottom(add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>) -> Self {
+        Self {
+            add: add.into_iter().collect(),
+            action: ChangeAction::None,
+            placement: Placement::Bottom,
+            dirty: false,
+        }
+    }
+
+    /// A change adding new layers relative to the current layer, at the time the change is applied.
+    ///
+    /// An offset of zero adds the layers directly above the current layer like `add`. Positive offsets skip as many layers above it, and negative offsets add the layers below it. An offset of `-1` adds them directly below the current layer.
+    /// The position is clamped to the ends of the stack, so large offsets add the layers at the top or the bottom.
+    pub fn add_at_offset(
+        offset: isize,
+        add: impl IntoIterator<Item = Box<dyn Layer<S, E>>>,
+    ) -> Self {
+        Self {
+            add: add.into_iter().collect(),
+            action: ChangeAction::None,
+            placement: Placement::Offset(offset),
+            dirty: false,
+        }
+    }
+}
+
+/// Prints the action and the number of added layers, since the layers themselves can't be printed.
+impl<S, E> fmt::Debug for Change<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Change")
+            .field("action", &self.action)
+            .field("add", &format_args!("{} layers", self.add.len()))
+            .finish()
+    }
+}
+
+impl<S, E> Change<S, E> {
+    /// Mark the manager as dirty when applying this change, so `LayerManager::needs_redraw` returns true, even if the stack doesn't change.
+    pub fn mark_dirty(mut self) -> Self {
+        self.dirty = true;
+        self
+    }
+
+    /// Turn the change into one, which also removes the current layer. Clearing all layers already removes it.
+    pub(crate) fn removing(mut self) -> Self {
+        if self.action != ChangeAction::Clear {
+            self.action = ChangeAction::Remove;
+        }
+        self
+    }
+}
+
+/// Observes the structural changes of the stack of a layer manager, set by `LayerManager::set_stack_observer`.
+///
+/// The methods are called synchronously while the manager changes the stack, for changes returned by layers and for methods like `push` alike.
+/// They only get shared access to the layers, so they can't change the stack.
+pub trait StackObserver<S, E> {
+    /// Called after a layer has been added at `index`.
+    fn on_added(&mut self, _index: usize, _layer: &dyn Layer<S, E>) {}
+
+    /// Called after the layer at `index` has been removed and detached.
+    fn on_removed(&mut self, _index: usize, _layer: &dyn Layer<S, E>) {}
+
+    /// Called after clearing the stack by a change, with the number of removed layers. `on_removed` has already been called for each of them.
+    fn on_cleared(&mut self, _count: usize) {}
+}
+
+/// A queue of changes, which can be stored in the state, so everything having access to the state can request changes.
+///
+/// The changes are applied by `LayerManager::drain_queue`.
+pub struct ChangeQueue<S, E> {
+    changes: Vec<Change<S, E>>,
+}
+
+impl<S, E> ChangeQueue<S, E> {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self {
+            changes: Vec::new(),
         }
     }
+
+    /// Add a change to the end of the queue.
+    pub fn push(&mut self, change: Change<S, E>) {
+        self.changes.push(change);
+    }
+
+    /// The number of queued changes.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Checks if no changes are queued.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl<S, E> Default for ChangeQueue<S, E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// A trait, every layer has to implement, in order to be used by the layer manager;
@@ -90,109 +355,5574 @@ pub trait Layer<S, E> {
 
     /// Executed for top layer and optionally for more layers. Most useful for click events.
     fn update(&mut self, _state: &mut S, _event: &E) -> Change<S, E>;
+
+    /// Like `update`, but can also produce an output for the caller of `LayerManager::update_collect`. Defaults to calling `update` without output.
+    ///
+    /// The output is type-erased on purpose: all layers of a stack share the type `dyn Layer<S, E>`, and an output type parameter would allow only one output type per stack.
+    /// So the type is checked at runtime, and the caller only receives outputs of the type it expects.
+    fn update_output(&mut self, state: &mut S, event: &E) -> (Change<S, E>, Option<Box<dyn Any>>) {
+        (self.update(state, event), None)
+    }
+
+    /// Called when the layer is added to a layer manager.
+    fn on_attach(&mut self) {}
+
+    /// Called when the layer is removed from a layer manager.
+    fn on_detach(&mut self) {}
+
+    /// Called when the layer stops being the top layer, because another layer has been added above it, or when it's added below the top layer.
+    fn on_cover(&mut self) {}
+
+    /// Called when the layer becomes the top layer again, because the layers above it have been removed.
+    ///
+    /// The manager only calls the hooks for the final state after an update or another operation, so intermediate tops don't notice anything.
+    fn on_uncover(&mut self) {}
+
+    /// Layers with a higher priority receive events in `update` first, regardless of their position in the stack.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// A bitmask of groups the layer belongs to, which can be used for bulk operations like `LayerManager::remove_by_tag`.
+    fn tags(&self) -> u32 {
+        0
+    }
+
+    /// The position of the layer in the stack used by `LayerManager::insert_z_ordered`. Layers with a higher z-order are placed above.
+    fn z_order(&self) -> i32 {
+        0
+    }
+
+    /// Checks if the layer needs a passive update. Layers returning `false` are skipped in the passive updates.
+    ///
+    /// This allows mostly static layers to only be redrawn when something changed.
+    /// The manager doesn't consider structural changes, so layers affected by them have to report it themselves.
+    fn needs_passive(&self) -> bool {
+        true
+    }
+
+    /// Checks if the layer is enabled. Disabled layers stay in the stack, but are skipped in both `update` and the passive updates.
+    ///
+    /// A disabled layer is treated as if it passed every event, so it never hides events from the layers below, even if it would consume them when enabled.
+    /// Layers supporting this store the flag themselves and toggle it in `set_enabled`.
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    /// Enable or disable the layer. Does nothing by default, so layers are always enabled unless they opt in.
+    fn set_enabled(&mut self, _enabled: bool) {}
+
+    /// Checks if the layer is sticky. The manager refuses to remove sticky layers, for example the shell of an application at the bottom of the stack.
+    ///
+    /// A sticky layer returning `Remove` stays in the stack, so replacing it only adds the new layers. Clearing the stack keeps sticky layers in place.
+    /// `LayerManager::pop` and `LayerManager::remove_at` refuse to remove them.
+    /// Explicit bulk operations of the manager like `LayerManager::truncate` and `LayerManager::remove_by_tag` still remove them.
+    fn sticky(&self) -> bool {
+        false
+    }
+
+    /// Checks if the layer is persistent. Persistent layers survive clearing the stack, for example a global input recorder.
+    ///
+    /// Unlike sticky layers, persistent layers can still be removed in any other way, including by returning `Remove` themselves.
+    fn is_persistent(&self) -> bool {
+        false
+    }
+
+    /// Checks if the layer is opaque, so it completely hides the layers below it when rendering.
+    ///
+    /// This is used by `LayerManager::active_layer_count`, and when input occlusion is enabled, events passed by opaque layers don't reach the layers below.
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    /// Called before the layer is removed by `LayerManager::pop` or `LayerManager::remove_at`. Returning `false` vetoes the removal.
+    ///
+    /// Removals by changes aren't vetoed, since a layer returning `Remove` decides about its removal itself.
+    fn on_remove_request(&mut self, _state: &mut S) -> bool {
+        true
+    }
+
+    /// Fallible variant of `update`, which is called by `LayerManager::try_update` instead. Defaults to calling `update`.
+    fn try_update(&mut self, state: &mut S, event: &E) -> Result<Change<S, E>, LayerError> {
+        Ok(self.update(state, event))
+    }
+
+    /// Called by `LayerManager::update_owned` to offer the ownership of the event to the layer, so it can move parts of it into itself instead of copying them.
+    ///
+    /// Returning the event passes it to the next layer. Defaults to passing it, so only layers implementing this can claim events.
+    fn take_event(&mut self, _state: &mut S, event: E) -> Result<Change<S, E>, E> {
+        Err(event)
+    }
+
+    /// Create an independent copy of this layer, if it supports it.
+    ///
+    /// Layers implementing `Clone` can support it by returning `Some(self.clone_box())`.
+    fn try_clone(&self) -> Option<Box<dyn Layer<S, E>>> {
+        None
+    }
+
+    /// A name identifying the kind of layer. Defaults to the type name.
+    fn name(&self) -> &'static str {
+        core::any::type_name::<Self>()
+    }
+
+    /// The type of the layer used by `LayerManager::find_type`. Like `name`, adapters and boxes report the type of the wrapped layer.
+    fn layer_type_id(&self) -> TypeId
+    where
+        Self: 'static,
+    {
+        TypeId::of::<Self>()
+    }
+
+    /// Serialize the state of this layer, if it supports it.
+    ///
+    /// Layers are trait objects, so they can't be deserialized generically.
+    /// Restoring a layer requires a registry, which creates a layer from its `name` and loads the saved data into it.
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<Vec<u8>> {
+        None
+    }
 }
 
-/// The layer manager deals with the layers you create.
-pub struct LayerManager<S, E>(ArrayVec<[LayerBox<S, E>; 10]>);
+/// Boxed layers are layers themselves, so adapters can wrap them.
+///
+/// A `Box<dyn Layer>` can be added to the manager directly. Boxing it again would only add another indirection.
+impl<S, E, L: Layer<S, E> + ?Sized> Layer<S, E> for Box<L> {
+    forward_layer!();
+}
 
-impl<S, E> LayerManager<S, E> {
-    /// Create a new layer manager containing specified initial layers.
-    pub fn new(layers: ArrayVec<[LayerBox<S, E>; 10]>) -> Self {
-        LayerManager::<S, E>(layers)
+impl<S, E, L: Layer<S, E> + ?Sized> Layer<S, E> for &mut L {
+    forward_layer!();
+}
+
+/// An optional layer behaves like the contained layer. If there is none, it passes every event.
+impl<S, E, L: Layer<S, E>> Layer<S, E> for Option<L> {
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        if let Some(layer) = self {
+            layer.passive_update(state, event)
+        }
     }
 
-    /// Checks if the layer manger is still active. When not active, the program should terminate or new layers should be added before calling `update` again.
-    pub fn is_active(&self) -> bool {
-        !self.0.is_empty()
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        match self {
+            Some(layer) => layer.update(state, event),
+            None => Change::pass(),
+        }
     }
 
-    /// Everytime the program recieves or generates an event, which should be handled by a layer, this method has to be called.
-    pub fn update(&mut self, state: &mut S, event: E) {
-        let count = self.0.len();
-        let mut i = count;
-        while i > 0 {
-            i -= 1;
-            let layer = &mut self.0[i];
-            let Change { add, action } = layer.update(state, &event);
-            let add_index = i + 1;
-            for (i, added) in add.into_iter().enumerate() {
-                self.0.insert(add_index + i, added);
-            }
-            use ChangeAction::*;
-            match action {
-                None => (),
-                Pass => continue,
-                Remove => {
-                    self.0.remove(i);
-                }
-                Clear => self.0.clear(),
-            }
-            break;
+    fn update_output(&mut self, state: &mut S, event: &E) -> (Change<S, E>, Option<Box<dyn Any>>) {
+        match self {
+            Some(layer) => layer.update_output(state, event),
+            None => (Change::pass(), None),
         }
+    }
 
-        for layer in self.0.iter_mut() {
-            layer.passive_update(state, &event);
+    fn on_attach(&mut self) {
+        if let Some(layer) = self {
+            layer.on_attach()
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::*;
+    fn on_detach(&mut self) {
+        if let Some(layer) = self {
+            layer.on_detach()
+        }
+    }
 
-    pub enum Event {
-        Idle,
-        Input,
-        Exit,
+    fn on_cover(&mut self) {
+        if let Some(layer) = self {
+            layer.on_cover()
+        }
     }
 
-    pub struct GlobalState;
+    fn on_uncover(&mut self) {
+        if let Some(layer) = self {
+            layer.on_uncover()
+        }
+    }
 
-    pub struct MainLayer;
+    fn priority(&self) -> i32 {
+        self.as_ref().map_or(0, Layer::priority)
+    }
 
-    impl Layer<GlobalState, Event> for MainLayer {
-        fn update(
-            &mut self,
-            _state: &mut GlobalState,
-            event: &Event,
-        ) -> Change<GlobalState, Event> {
-            match event {
-                Event::Input => Change::add(vec![Box::new(TopLayer)]),
-                Event::Idle => Change::none(),
-                Event::Exit => Change::remove(),
-            }
+    fn tags(&self) -> u32 {
+        self.as_ref().map_or(0, Layer::tags)
+    }
+
+    fn z_order(&self) -> i32 {
+        self.as_ref().map_or(0, Layer::z_order)
+    }
+
+    fn needs_passive(&self) -> bool {
+        self.as_ref().is_some_and(Layer::needs_passive)
+    }
+
+    fn enabled(&self) -> bool {
+        self.as_ref().is_none_or(Layer::enabled)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        if let Some(layer) = self {
+            layer.set_enabled(enabled)
         }
     }
 
-    pub struct TopLayer;
+    fn sticky(&self) -> bool {
+        self.as_ref().is_some_and(Layer::sticky)
+    }
 
-    impl Layer<GlobalState, Event> for TopLayer {
-        fn update(
-            &mut self,
-            _state: &mut GlobalState,
-            event: &Event,
-        ) -> Change<GlobalState, Event> {
-            match event {
-                Event::Input => Change::pass(),
-                Event::Idle => Change::none(),
-                Event::Exit => Change::remove(),
-            }
+    fn is_persistent(&self) -> bool {
+        self.as_ref().is_some_and(Layer::is_persistent)
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.as_ref().is_some_and(Layer::is_opaque)
+    }
+
+    fn on_remove_request(&mut self, state: &mut S) -> bool {
+        self.as_mut()
+            .is_none_or(|layer| layer.on_remove_request(state))
+    }
+
+    fn try_update(&mut self, state: &mut S, event: &E) -> Result<Change<S, E>, LayerError> {
+        match self {
+            Some(layer) => layer.try_update(state, event),
+            None => Ok(Change::pass()),
         }
     }
 
-/*
-    #[test]
-    fn example() {
-        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
-        let mut state = GlobalState;
+    fn take_event(&mut self, state: &mut S, event: E) -> Result<Change<S, E>, E> {
+        match self {
+            Some(layer) => layer.take_event(state, event),
+            None => Err(event),
+        }
+    }
 
-        manager.update(&mut state, Event::Idle);
-        manager.update(&mut state, Event::Input);
-        manager.update(&mut state, Event::Idle);
+    fn try_clone(&self) -> Option<Box<dyn Layer<S, E>>> {
+        self.as_ref().and_then(Layer::try_clone)
+    }
 
-        while manager.is_active() {
-            manager.update(&mut state, Event::Exit);
+    fn name(&self) -> &'static str {
+        match self {
+            Some(layer) => layer.name(),
+            None => core::any::type_name::<Self>(),
+        }
+    }
+
+    fn layer_type_id(&self) -> TypeId
+    where
+        Self: 'static,
+    {
+        match self {
+            Some(layer) => layer.layer_type_id(),
+            None => TypeId::of::<Self>(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<Vec<u8>> {
+        self.as_ref().and_then(Layer::save)
+    }
+}
+
+/// A helper trait for cloning layers into boxes, implemented for all cloneable layers.
+pub trait CloneLayer<S, E> {
+    /// Clone the layer into a new box.
+    fn clone_box(&self) -> Box<dyn Layer<S, E>>;
+}
+
+impl<S, E, T: Layer<S, E> + Clone + 'static> CloneLayer<S, E> for T {
+    fn clone_box(&self) -> Box<dyn Layer<S, E>> {
+        Box::new(self.clone())
+    }
+}
+
+/// The error returned when trying to clone a stack containing a layer, which can't be cloned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NotCloneable {
+    /// The index of the first layer, which can't be cloned.
+    pub index: usize,
+    /// The name of the layer.
+    pub name: &'static str,
+}
+
+/// Independent copies of all layers of a stack, created by `LayerManager::snapshot`.
+pub struct StackSnapshot<S, E> {
+    layers: Vec<(Box<dyn Layer<S, E>>, bool)>,
+}
+
+impl<S, E> StackSnapshot<S, E> {
+    /// The number of layers in the snapshot.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Checks if the snapshot contains no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+/// The result of dispatching an event by `LayerManager::update`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    /// A layer consumed the event.
+    Consumed,
+    /// Every layer passed the event or was skipped, for example because it's asleep.
+    PassedThrough,
+    /// There were no layers to dispatch the event to.
+    Empty,
+    /// The manager is paused, so the event hasn't been dispatched.
+    Paused,
+    /// The event filter dropped the event, so it hasn't been dispatched.
+    Dropped,
+}
+
+/// How `LayerManager::update_routed` delivers an event to the layers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Routing {
+    /// The layers are asked until one consumes the event, like in `LayerManager::update`.
+    Consume,
+    /// Every layer receives the event, like in `LayerManager::broadcast`.
+    Broadcast,
+    /// Only the layer, which would be asked first in `LayerManager::update`, receives the event. When it passes the event, no other layer receives it.
+    TopOnly,
+}
+
+/// A trait for events, which decide how they are delivered to the layers by `LayerManager::update_routed`.
+pub trait EventRouting {
+    /// The routing of this event. Defaults to `Routing::Consume`, so events are dispatched like in `LayerManager::update`.
+    fn routing(&self) -> Routing {
+        Routing::Consume
+    }
+}
+
+/// What `LayerManager::update_batch` does with the next event, as decided by the coalescer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CoalesceDecision<E> {
+    /// The coalescer merged the next event into the pending event, so the pending event stays pending.
+    Merge,
+    /// Dispatch the pending event and keep the returned event pending instead.
+    Flush(E),
+    /// Dispatch the pending event and the returned event directly afterwards.
+    DispatchBoth(E),
+}
+
+/// The reason `LayerManager::run` stopped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The event source didn't return any more events.
+    SourceExhausted,
+    /// The stack became empty.
+    Inactive,
+}
+
+/// The error returned when an operation requires at least one layer, but the stack is empty.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Inactive;
+
+/// Identifies a layer in a layer manager for as long as it stays on the stack.
+///
+/// Every layer added to the manager gets a new id, so the id of a removed layer never refers to another layer of the same manager.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LayerId(u64);
+
+/// An error returned by a layer in `try_update`.
+///
+/// It can contain any error type, which can be recovered by downcasting.
+pub struct LayerError(Box<dyn Any>);
+
+impl LayerError {
+    /// Wrap some error value.
+    pub fn new<T: Any>(error: T) -> Self {
+        Self(Box::new(error))
+    }
+
+    /// Checks if the error is of type `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    /// Get a reference to the error, if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+
+    /// Get the error back, if it is of type `T`.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        self.0.downcast().map(|error| *error).map_err(Self)
+    }
+}
+
+impl fmt::Debug for LayerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LayerError")
+    }
+}
+
+/// What the layer manager does, when a layer fails in `try_update`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop the dispatch and return the error immediately.
+    Abort,
+    /// Continue with the next layer, as if the failing layer passed the event.
+    Skip,
+    /// Remove the failing layer and continue with the next layer.
+    Remove,
+}
+
+/// Which layers the layer manager updates passively after dispatching an event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassivePolicy {
+    /// Update all layers passively, including the layers added while dispatching the event.
+    All,
+    /// Skip the layers added while dispatching the event, so they are first updated passively with the next event.
+    SkipNewlyAdded,
+}
+
+/// The order, in which the layer manager asks layers of the same priority to handle an event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DispatchOrder {
+    /// From top to bottom, so upper layers can hide the event from lower layers.
+    TopDown,
+    /// From bottom to top, so lower layers can preprocess the event for upper layers like a pipeline.
+    BottomUp,
+}
+
+/// The order, in which the layer manager updates the layers passively.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassiveOrder {
+    /// From bottom to top, so upper layers are rendered above lower layers.
+    #[doc(alias = "BottomToTop")]
+    BottomUp,
+    /// From top to bottom, so upper layers can write values into the state, which lower layers read.
+    #[doc(alias = "TopToBottom")]
+    TopDown,
+}
+
+/// The failure of a layer in `try_update`.
+#[derive(Debug)]
+pub struct LayerFailure {
+    /// The index of the failing layer at the time it failed.
+    pub index: usize,
+    /// The name of the failing layer.
+    pub name: &'static str,
+    /// The policy, which has been applied.
+    pub policy: ErrorPolicy,
+    /// The error returned by the layer.
+    pub error: LayerError,
+}
+
+/// What the layer manager does, when a change would exceed the depth limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DepthPolicy {
+    /// Drop the excess layers silently, which can be checked by `LayerManager::dropped`.
+    Drop,
+    /// Panic in debug builds. In release builds, the excess layers are dropped and the violation is reported like `Report`.
+    Panic,
+    /// Drop the excess layers and report the violation through `LayerManager::last_error`.
+    Report,
+}
+
+/// A change, which would have exceeded the depth limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DepthExceeded {
+    /// The index of the layer returning the change at the time it returned it.
+    pub index: usize,
+    /// The name of the layer returning the change.
+    pub name: &'static str,
+    /// The number of dropped layers.
+    pub dropped: usize,
+}
+
+struct Entry<S, E> {
+    layer: Box<dyn Layer<S, E>>,
+    id: LayerId,
+    asleep: bool,
+    fresh: bool,
+    covered: bool,
+}
+
+impl<S, E> Entry<S, E> {
+    fn new(mut layer: Box<dyn Layer<S, E>>, id: LayerId) -> Self {
+        layer.on_attach();
+        debug!("attached layer");
+        Self {
+            layer,
+            id,
+            asleep: false,
+            fresh: false,
+            covered: false,
+        }
+    }
+}
+
+/// Marks a manager as updating, until it's dropped, even if a layer panics.
+struct Updating<'a, S, E, A: Allocator>(&'a mut LayerManager<S, E, A>);
+
+impl<S, E, A: Allocator> Drop for Updating<'_, S, E, A> {
+    fn drop(&mut self) {
+        self.0.updating = false;
+    }
+}
+
+enum Asked<S, E> {
+    Changed(Change<S, E>),
+    Failed,
+    Panicked,
+}
+
+type UnhandledHandler<S, E> = Box<dyn FnMut(&mut S, &E)>;
+
+type Observer = Box<dyn FnMut(usize, &ChangeAction)>;
+
+type EventFilter<S, E> = Box<dyn FnMut(&mut S, E) -> Option<E>>;
+
+type Coalescer<E> = Box<dyn FnMut(&mut E, E) -> CoalesceDecision<E>>;
+
+type Panic = Box<dyn Any + Send>;
+
+#[cfg(feature = "std")]
+fn catching<T>(catch: bool, f: impl FnOnce() -> T) -> Result<T, Panic> {
+    if catch {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+    } else {
+        Ok(f())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+fn catching<T>(_catch: bool, f: impl FnOnce() -> T) -> Result<T, Panic> {
+    Ok(f())
+}
+
+#[cfg(feature = "record")]
+type Recording<E> = (Vec<E>, fn(&E) -> E);
+
+/// The events recorded by a layer manager between `start_recording` and `stop_recording`, in the order they have been dispatched.
+///
+/// The events are a plain list, so they can be inspected, edited or serialized by the user.
+#[cfg(feature = "record")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventTape<E>(pub Vec<E>);
+
+/// The layer manager deals with the layers you create.
+///
+/// The stack is stored by the allocator `A`, which can only be chosen with the `allocator_api` feature using `new_in`.
+/// The layers and the changes are always allocated by the global allocator, so layers added by a `Change` are moved into the stack of the manager.
+/// On systems with multiple heaps, the global allocator has to choose the heap of the layers.
+pub struct LayerManager<S, E, A: Allocator = Global> {
+    layers: Stack<Entry<S, E>, A>,
+    error_policy: ErrorPolicy,
+    passive_policy: PassivePolicy,
+    passive_order: PassiveOrder,
+    dispatch_order: DispatchOrder,
+    input_occlusion: bool,
+    max_depth: Option<usize>,
+    depth_policy: DepthPolicy,
+    last_error: Option<DepthExceeded>,
+    dropped: usize,
+    on_unhandled: Option<UnhandledHandler<S, E>>,
+    event_filter: Option<EventFilter<S, E>>,
+    coalescer: Option<Coalescer<E>>,
+    observer: Option<Observer>,
+    stack_observer: Option<Box<dyn StackObserver<S, E>>>,
+    removed: Option<Vec<Box<dyn Layer<S, E>>>>,
+    queued: Vec<E>,
+    coalesce: Option<fn(&E, &E) -> bool>,
+    paused: bool,
+    paused_passive: bool,
+    dirty: bool,
+    catch_panics: bool,
+    last_panic: Option<Panic>,
+    history: Option<History<S, E>>,
+    editing: bool,
+    updating: bool,
+    next_id: u64,
+    focus: Option<LayerId>,
+    #[cfg(feature = "record")]
+    recording: Option<Recording<E>>,
+    #[cfg(feature = "std")]
+    mailbox: mailbox::Mailbox<S, E>,
+}
+
+impl<S, E> LayerManager<S, E> {
+    /// Create a new layer manager containing specified initial layers.
+    pub fn new(layers: Vec<Box<dyn Layer<S, E>>>) -> Self {
+        Self::new_in(layers, Global)
+    }
+
+    /// Create a new layer manager containing a single root layer.
+    pub fn from_layer(layer: Box<dyn Layer<S, E>>) -> Self {
+        Self::new(alloc::vec![layer])
+    }
+
+    /// Create a new layer manager, which never grows beyond `max_depth` layers.
+    ///
+    /// When the changes of a layer would exceed the limit, only the lowest of the added layers are kept until the limit is reached, and the rest is dropped.
+    /// The initial layers are always kept.
+    pub fn with_max_depth(layers: Vec<Box<dyn Layer<S, E>>>, max_depth: usize) -> Self {
+        Self {
+            max_depth: Some(max_depth),
+            ..Self::new(layers)
+        }
+    }
+}
+
+impl<S, E, A: Allocator> LayerManager<S, E, A> {
+    /// The maximum number of times layers added by `Change::add_and_dispatch` receive the event being dispatched in a single update.
+    ///
+    /// Afterwards further layers are still added, but don't receive the event anymore.
+    pub const MAX_REDISPATCH: usize = 16;
+
+    /// Create a new layer manager containing specified initial layers, whose stack is stored by `alloc`.
+    ///
+    /// Only the stack uses `alloc`. The boxes of the layers stay in the global allocator.
+    pub fn new_in(layers: Vec<Box<dyn Layer<S, E>>>, alloc: A) -> Self {
+        let mut stack = Stack::new_in(alloc);
+        stack.extend(
+            layers
+                .into_iter()
+                .enumerate()
+                .map(|(index, layer)| Entry::new(layer, LayerId(index as u64))),
+        );
+        let mut manager = Self {
+            next_id: stack.len() as u64,
+            layers: stack,
+            error_policy: ErrorPolicy::Abort,
+            passive_policy: PassivePolicy::All,
+            passive_order: PassiveOrder::BottomUp,
+            dispatch_order: DispatchOrder::TopDown,
+            input_occlusion: false,
+            max_depth: None,
+            depth_policy: DepthPolicy::Drop,
+            last_error: None,
+            dropped: 0,
+            on_unhandled: None,
+            event_filter: None,
+            coalescer: None,
+            observer: None,
+            stack_observer: None,
+            removed: None,
+            queued: Vec::new(),
+            coalesce: None,
+            paused: false,
+            paused_passive: false,
+            dirty: false,
+            catch_panics: false,
+            last_panic: None,
+            history: None,
+            editing: false,
+            updating: false,
+            focus: None,
+            #[cfg(feature = "record")]
+            recording: None,
+            #[cfg(feature = "std")]
+            mailbox: std::sync::mpsc::channel(),
+        };
+        manager.refresh_cover();
+        manager
+    }
+
+    /// The maximum number of layers, if limited.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Set the maximum number of layers, or remove the limit using `None`.
+    ///
+    /// Like in `with_max_depth`, the limit only applies to layers added later, so existing layers are kept.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// The policy for handling changes exceeding the depth limit.
+    pub fn depth_policy(&self) -> DepthPolicy {
+        self.depth_policy
+    }
+
+    /// Set the policy for handling changes exceeding the depth limit. Defaults to `DepthPolicy::Drop`.
+    pub fn set_depth_policy(&mut self, policy: DepthPolicy) {
+        self.depth_policy = policy;
+    }
+
+    /// The last change, which exceeded the depth limit during the last update, if reported by the depth policy.
+    pub fn last_error(&self) -> Option<DepthExceeded> {
+        self.last_error
+    }
+
+    /// The number of layers, which have been dropped because of the depth limit during the last update.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// The number of layers currently on the stack.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Checks if the stack contains no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// The number of layers the stack can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.layers.capacity()
+    }
+
+    /// Reserve capacity for at least `additional` more layers.
+    pub fn reserve(&mut self, additional: usize) {
+        self.layers.reserve(additional);
+    }
+
+    /// Shrink the capacity of the stack as much as possible, releasing memory after many layers have been removed.
+    pub fn shrink_to_fit(&mut self) {
+        self.layers.shrink_to_fit();
+    }
+
+    /// The index of the top layer, which receives events first, or `None` if the stack is empty.
+    pub fn active_index(&self) -> Option<usize> {
+        self.layers.len().checked_sub(1)
+    }
+
+    /// Checks if the layer manger is still active. When not active, the program should terminate or new layers should be added before calling `update` again.
+    pub fn is_active(&self) -> bool {
+        !self.layers.is_empty()
+    }
+
+    /// Collect the saved state of all layers, which support saving, from bottom to top.
+    ///
+    /// Layers returning `None` from `save` are skipped. See `Layer::save` for how to restore them.
+    #[cfg(feature = "serde")]
+    pub fn save_layers(&self) -> Vec<Vec<u8>> {
+        self.layers
+            .iter()
+            .filter_map(|entry| entry.layer.save())
+            .collect()
+    }
+
+    /// Copy all layers by `Layer::try_clone` to restore them later by `restore`, including which layers are asleep.
+    ///
+    /// Fails if any layer returns `None` from `try_clone`.
+    pub fn snapshot(&self) -> Result<StackSnapshot<S, E>, NotCloneable> {
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for (index, entry) in self.layers.iter().enumerate() {
+            let layer = entry.layer.try_clone().ok_or(NotCloneable {
+                index,
+                name: entry.layer.name(),
+            })?;
+            layers.push((layer, entry.asleep));
+        }
+        Ok(StackSnapshot { layers })
+    }
+
+    /// Replace the whole stack by the layers of a snapshot created by `snapshot`.
+    ///
+    /// All current layers are removed from top to bottom, even if they are sticky, and the restored layers are attached and get new ids.
+    pub fn restore(&mut self, snapshot: StackSnapshot<S, E>) {
+        self.batch(|manager| {
+            manager.truncate(0);
+            let count = snapshot.layers.len();
+            for (layer, asleep) in snapshot.layers {
+                let id = manager.next_id();
+                manager.layers.push(Entry {
+                    asleep,
+                    ..Entry::new(layer, id)
+                });
+            }
+            manager.record(Edit::Inserted { index: 0, count });
+            manager.notify_added(0..count);
+            manager.dirty = true;
+        });
+    }
+
+    /// Create an independent copy of the stack, including which layers are asleep.
+    /// The settings of the manager aren't copied.
+    ///
+    /// Fails if any layer returns `None` from `try_clone`.
+    pub fn try_clone_stack(&self) -> Result<Self, NotCloneable>
+    where
+        A: Clone,
+    {
+        let layers = self.snapshot()?.layers;
+        let mut manager = Self::new_in(Vec::new(), self.layers.allocator().clone());
+        manager
+            .layers
+            .extend(
+                layers
+                    .into_iter()
+                    .zip(self.layers.iter())
+                    .map(|((layer, asleep), entry)| Entry {
+                        asleep,
+                        covered: entry.covered,
+                        ..Entry::new(layer, entry.id)
+                    }),
+            );
+        manager.next_id = self.next_id;
+        Ok(manager)
+    }
+
+    /// Replace the layers in `range` by `replacement` like `Vec::splice`, and return the removed layers from bottom to top.
+    ///
+    /// The removed layers are detached from top to bottom, even if they are sticky, and the replacement is attached afterwards.
+    /// Cover hooks are only called once for the resulting stack.
+    /// Panics if the range is out of bounds.
+    pub fn splice(
+        &mut self,
+        range: Range<usize>,
+        replacement: Vec<Box<dyn Layer<S, E>>>,
+    ) -> Vec<Box<dyn Layer<S, E>>> {
+        assert!(
+            range.start <= range.end && range.end <= self.layers.len(),
+            "splice range {:?} out of bounds for {} layers",
+            range,
+            self.layers.len()
+        );
+        let start = range.start;
+        self.dirty = true;
+        let mut removed: Vec<_> = range.rev().map(|index| self.remove_layer(index)).collect();
+        removed.reverse();
+        if !removed.is_empty() {
+            self.lose_history();
+        }
+        let count = replacement.len();
+        if count > 0 {
+            self.record(Edit::Inserted {
+                index: start,
+                count,
+            });
+        }
+        let first = self.next_id;
+        self.next_id += count as u64;
+        self.layers.splice(
+            start..start,
+            replacement
+                .into_iter()
+                .zip(first..)
+                .map(|(layer, id)| Entry::new(layer, LayerId(id))),
+        );
+        self.notify_added(start..start + count);
+        self.refresh_cover();
+        removed
+    }
+
+    /// Remove all layers above the first `len` layers from top to bottom, calling `on_detach` for each of them.
+    ///
+    /// Does nothing if there are no more than `len` layers. `truncate(0)` removes all layers.
+    pub fn truncate(&mut self, len: usize) {
+        while self.layers.len() > len {
+            self.discard(self.layers.len() - 1);
+        }
+        self.refresh_cover();
+    }
+
+    /// Remove all layers except the bottom layer, calling `on_detach` for each of them, and add `new_top` above the bottom layer.
+    ///
+    /// Fails without adding any layers if the stack is empty.
+    pub fn reset_to_root(&mut self, new_top: Vec<Box<dyn Layer<S, E>>>) -> Result<(), Inactive> {
+        if self.layers.is_empty() {
+            return Err(Inactive);
+        }
+        self.batch(|manager| {
+            manager.truncate(1);
+            manager.dropped = 0;
+            manager.insert_added(1, new_top);
+        });
+        Ok(())
+    }
+
+    /// Edit the stack with multiple operations at once, for example to remove two layers and add three new ones.
+    ///
+    /// Each operation calls `on_attach` and `on_detach` as usual, but `on_cover` and `on_uncover` are only called once for the final state of the stack after `f` returns.
+    pub fn transaction<R>(&mut self, f: impl FnOnce(&mut StackEditor<S, E, A>) -> R) -> R {
+        self.batch(|manager| f(&mut StackEditor::new(manager)))
+    }
+
+    /// Add a layer on top of the stack.
+    ///
+    /// The depth limit only applies to layers added by changes.
+    pub fn push(&mut self, layer: Box<dyn Layer<S, E>>) {
+        self.insert_at(self.layers.len(), layer);
+    }
+
+    /// Insert a layer at `index`, moving all layers above it up.
+    ///
+    /// The depth limit only applies to layers added by changes.
+    /// Panics if `index` is greater than the number of layers.
+    pub fn insert_at(&mut self, index: usize, layer: Box<dyn Layer<S, E>>) {
+        let id = self.next_id();
+        self.layers.insert(index, Entry::new(layer, id));
+        self.record(Edit::Inserted { index, count: 1 });
+        self.notify_added(index..index + 1);
+        self.dirty = true;
+        debug!("inserted layer at {}, depth {}", index, self.layers.len());
+        self.refresh_cover();
+    }
+
+    /// Replace the top layer with `layer` and return the old top layer, calling `on_detach` for the old and `on_attach` for the new layer.
+    ///
+    /// If the stack is empty, `layer` is just added and `None` is returned.
+    pub fn replace_top(&mut self, layer: Box<dyn Layer<S, E>>) -> Option<Box<dyn Layer<S, E>>> {
+        self.batch(|manager| {
+            let old = manager.active_index().map(|index| {
+                manager.lose_history();
+                manager.remove_layer(index)
+            });
+            manager.push(layer);
+            old
+        })
+    }
+
+    /// Remove the top layer and return it, unless it's sticky or vetoes its removal in `Layer::on_remove_request`.
+    ///
+    /// Returns `None` if the stack is empty or the removal has been refused.
+    pub fn pop(&mut self, state: &mut S) -> Option<Box<dyn Layer<S, E>>> {
+        let index = self.active_index()?;
+        self.remove_at(index, state)
+    }
+
+    /// Remove top layers, until `f` returns true for the top layer or the stack is empty, and return the number of removed layers.
+    ///
+    /// Like `truncate`, this doesn't consult `Layer::on_remove_request`, but it stops at the first sticky layer.
+    pub fn pop_until<F: FnMut(&dyn Layer<S, E>) -> bool>(&mut self, mut f: F) -> usize {
+        let mut removed = 0;
+        while let Some(entry) = self.layers.last() {
+            if entry.layer.sticky() || f(&*entry.layer) {
+                break;
+            }
+            self.discard(self.layers.len() - 1);
+            removed += 1;
+        }
+        self.refresh_cover();
+        removed
+    }
+
+    /// Remove the layer at `index` and return it, unless it's sticky or vetoes its removal in `Layer::on_remove_request`.
+    ///
+    /// Returns `None` if there's no layer at `index` or the removal has been refused.
+    pub fn remove_at(&mut self, index: usize, state: &mut S) -> Option<Box<dyn Layer<S, E>>> {
+        let entry = self.layers.get_mut(index)?;
+        if entry.layer.sticky() {
+            debug!("layer at {} is sticky", index);
+            return None;
+        }
+        if !entry.layer.on_remove_request(state) {
+            debug!("removal of layer at {} vetoed", index);
+            return None;
+        }
+        let layer = self.remove_layer(index);
+        self.lose_history();
+        self.refresh_cover();
+        Some(layer)
+    }
+
+    /// Insert a layer, so the stack stays sorted by `Layer::z_order` from bottom to top, and return its index.
+    ///
+    /// The new layer is placed above all layers with the same z-order.
+    /// This assumes the stack is already sorted. Adding layers by `push`, `insert_at` or changes may break this.
+    pub fn insert_z_ordered(&mut self, layer: Box<dyn Layer<S, E>>) -> usize {
+        let z_order = layer.z_order();
+        let index = self
+            .layers
+            .partition_point(|entry| entry.layer.z_order() <= z_order);
+        self.insert_at(index, layer);
+        index
+    }
+
+    /// Sort the whole stack by `Layer::z_order` from bottom to top, keeping the order of layers with the same z-order.
+    ///
+    /// This restores the order expected by `insert_z_ordered` after adding layers in other ways.
+    pub fn sort_by_z(&mut self) {
+        self.sort_by_key(|layer| layer.z_order());
+    }
+
+    /// Sort the whole stack by a key from bottom to top, keeping the order of layers with the same key.
+    ///
+    /// No layer is attached or detached, but cover hooks are called if the top layer changes.
+    pub fn sort_by_key<K: Ord, F: FnMut(&dyn Layer<S, E>) -> K>(&mut self, mut f: F) {
+        let mut order: Vec<usize> = (0..self.layers.len()).collect();
+        order.sort_by_key(|&index| f(&*self.layers[index].layer));
+        let mut positions: Vec<usize> = (0..self.layers.len()).collect();
+        let mut originals = positions.clone();
+        for (target, index) in order.into_iter().enumerate() {
+            let current = positions[index];
+            if current == target {
+                continue;
+            }
+            self.layers.swap(target, current);
+            self.record(Edit::Swapped(target, current));
+            let displaced = originals[target];
+            originals.swap(target, current);
+            positions[displaced] = current;
+            positions[index] = target;
+            self.dirty = true;
+        }
+        self.refresh_cover();
+    }
+
+    /// Remove all layers having any of the tags in `mask` from top to bottom, calling `on_detach` for each of them.
+    ///
+    /// Returns the number of removed layers.
+    pub fn remove_by_tag(&mut self, mask: u32) -> usize {
+        let mut removed = 0;
+        for index in (0..self.layers.len()).rev() {
+            if self.layers[index].layer.tags() & mask != 0 {
+                self.discard(index);
+                removed += 1;
+            }
+        }
+        self.refresh_cover();
+        removed
+    }
+
+    /// The number of layers, which are live for rendering.
+    ///
+    /// Counts the layers from top to bottom, which are enabled according to `Layer::enabled`, and stops after the first enabled layer, which is opaque according to `Layer::is_opaque`.
+    /// Disabled opaque layers don't hide anything. Sleeping layers are counted, since they are still updated passively, and `Layer::needs_passive` isn't considered.
+    pub fn active_layer_count(&self) -> usize {
+        let mut count = 0;
+        for entry in self.layers.iter().rev() {
+            if !entry.layer.enabled() {
+                continue;
+            }
+            count += 1;
+            if entry.layer.is_opaque() {
+                break;
+            }
+        }
+        count
+    }
+
+    /// The number of layers having any of the tags in `mask`.
+    pub fn count_by_tag(&self, mask: u32) -> usize {
+        self.iter().filter(|layer| layer.tags() & mask != 0).count()
+    }
+
+    /// The number of layers satisfying a predicate.
+    pub fn count<F: FnMut(&dyn Layer<S, E>) -> bool>(&self, mut f: F) -> usize {
+        self.iter().filter(|layer| f(*layer)).count()
+    }
+
+    /// Checks if any layer has any of the tags in `mask`.
+    pub fn any_with_tag(&self, mask: u32) -> bool {
+        self.iter().any(|layer| layer.tags() & mask != 0)
+    }
+
+    /// Checks if the stack contains a layer of type `T`.
+    pub fn contains_type<T: Layer<S, E> + 'static>(&self) -> bool
+    where
+        S: 'static,
+        E: 'static,
+    {
+        self.find_type::<T>().is_some()
+    }
+
+    /// Find the index of the topmost layer of type `T`, for example to avoid opening the same screen twice.
+    pub fn find_type<T: Layer<S, E> + 'static>(&self) -> Option<usize>
+    where
+        S: 'static,
+        E: 'static,
+    {
+        self.layers
+            .iter()
+            .rposition(|entry| entry.layer.layer_type_id() == TypeId::of::<T>())
+    }
+
+    /// Find the index of the topmost layer satisfying a predicate, scanning from top to bottom.
+    pub fn rfind<F: FnMut(&dyn Layer<S, E>) -> bool>(&self, mut f: F) -> Option<usize> {
+        self.layers.iter().rposition(|entry| f(&*entry.layer))
+    }
+
+    /// Iterate over all layers from bottom to top.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Layer<S, E>> + '_ {
+        self.layers
+            .iter()
+            .map(|entry| &*entry.layer as &dyn Layer<S, E>)
+    }
+
+    /// Fold all layers from bottom to top into an accumulator.
+    pub fn fold<B, F: FnMut(B, &dyn Layer<S, E>) -> B>(&self, init: B, f: F) -> B {
+        self.iter().fold(init, f)
+    }
+
+    /// Call a function for all layers from bottom to top.
+    pub fn for_each<F: FnMut(&dyn Layer<S, E>)>(&self, f: F) {
+        self.iter().for_each(f)
+    }
+
+    /// Checks if the layer at `index` is asleep.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn is_asleep(&self, index: usize) -> bool {
+        self.layers[index].asleep
+    }
+
+    /// Put the layer at `index` to sleep or wake it up.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_asleep(&mut self, index: usize, asleep: bool) {
+        self.layers[index].asleep = asleep;
+    }
+
+    /// Enable or disable the layer at `index` by calling its `set_enabled`. Layers not supporting it stay enabled.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.layers[index].layer.set_enabled(enabled);
+    }
+
+    /// Everytime the program recieves or generates an event, which should be handled by a layer, this method has to be called.
+    ///
+    /// Layers are asked in order of descending priority, layers of the same priority from top to bottom.
+    /// Sleeping layers are skipped as if they passed the event.
+    ///
+    /// The stack isn't changed while the layers are asked. Afterwards the changes are applied in the order the layers have been asked,
+    /// first the layers added by passing layers and the layers removed by the error policy, and finally the change of the consuming layer.
+    /// The position of every layer is tracked while applying the changes, so each change applies to the layer returning it.
+    ///
+    /// Afterwards all layers are updated passively from bottom to top.
+    ///
+    /// Updating an empty manager does nothing except calling the unhandled handler, and returns `Dispatch::Empty`.
+    /// So loops calling `update` should check `is_active` to terminate, or use `update_checked`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a layer reaches back into the manager it's part of, for example through a pointer stored in the state, and updates it again.
+    /// Such a nested update would invalidate the positions of the layers being dispatched to, so it's rejected instead of queued.
+    /// This applies to all methods dispatching events to the layers. Layers wanting to change the stack of their own manager should return changes or use a `ChangeQueue`.
+    pub fn update(&mut self, state: &mut S, event: E) -> Dispatch {
+        self.run_update(state, event, |layer, state, event| {
+            Ok(layer.update(state, event))
+        })
+        .0
+    }
+
+    /// Like `update`, but calls `after` with the manager and the state right afterwards and returns its result.
+    ///
+    /// This allows rendering the resulting stack directly after dispatching the event.
+    pub fn update_with<R>(
+        &mut self,
+        state: &mut S,
+        event: E,
+        after: impl FnOnce(&mut Self, &mut S) -> R,
+    ) -> R {
+        self.update(state, event);
+        after(self, state)
+    }
+
+    /// Update the manager with multiple events in order, like calling `update` for each of them.
+    ///
+    /// Stops as soon as the manager becomes inactive. The remaining events aren't taken from the iterator.
+    /// Returns the number of events, which have been dispatched.
+    ///
+    /// When a coalescer is set, consecutive events can be merged before any layer receives them, and an event pending when the manager becomes inactive is dropped.
+    pub fn update_batch(&mut self, state: &mut S, events: impl IntoIterator<Item = E>) -> usize {
+        let mut events = events.into_iter();
+        let mut count = 0;
+        let mut coalescer = match self.coalescer.take() {
+            Some(coalescer) => coalescer,
+            None => {
+                while self.is_active() {
+                    match events.next() {
+                        Some(event) => self.update(state, event),
+                        None => break,
+                    };
+                    count += 1;
+                }
+                return count;
+            }
+        };
+        let mut pending = None;
+        while self.is_active() {
+            let event = match events.next() {
+                Some(event) => event,
+                None => break,
+            };
+            let mut previous = match pending.take() {
+                Some(previous) => previous,
+                None => {
+                    pending = Some(event);
+                    continue;
+                }
+            };
+            let next = match coalescer(&mut previous, event) {
+                CoalesceDecision::Merge => {
+                    pending = Some(previous);
+                    continue;
+                }
+                CoalesceDecision::Flush(event) => {
+                    pending = Some(event);
+                    None
+                }
+                CoalesceDecision::DispatchBoth(event) => Some(event),
+            };
+            self.update(state, previous);
+            count += 1;
+            if let Some(event) = next {
+                if self.is_active() {
+                    self.update(state, event);
+                    count += 1;
+                }
+            }
+        }
+        if let Some(event) = pending {
+            if self.is_active() {
+                self.update(state, event);
+                count += 1;
+            }
+        }
+        self.coalescer = Some(coalescer);
+        count
+    }
+
+    /// Update the manager with events pulled from `source`, until the source returns `None` or the manager becomes inactive.
+    ///
+    /// The source receives the state, so it can take events from a queue stored in it. No event is pulled after the manager became inactive.
+    pub fn run(
+        &mut self,
+        state: &mut S,
+        mut source: impl FnMut(&mut S) -> Option<E>,
+    ) -> StopReason {
+        while self.is_active() {
+            match source(state) {
+                Some(event) => self.update(state, event),
+                None => return StopReason::SourceExhausted,
+            };
+        }
+        StopReason::Inactive
+    }
+
+    /// Queue an event to be dispatched by the next `flush`, replacing the last queued event, if it coalesces with it.
+    ///
+    /// This is useful for events arriving faster than they are handled, like pointer moves. Queued events are separate from events passed to `update` directly.
+    /// By default, every event replaces the last queued event, so only the latest event is dispatched.
+    pub fn queue(&mut self, event: E) {
+        let coalesce = match (&self.coalesce, self.queued.last()) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(coalesce), Some(last)) => coalesce(last, &event),
+        };
+        if coalesce {
+            self.queued.pop();
+        }
+        self.queued.push(event);
+    }
+
+    /// Set a predicate deciding if a queued event is replaced by a new event. It receives the last queued event and the new one.
+    pub fn set_coalesce(&mut self, coalesce: fn(&E, &E) -> bool) {
+        self.coalesce = Some(coalesce);
+    }
+
+    /// Dispatch all queued events in order like `update_batch` and return the number of dispatched events.
+    ///
+    /// Events still queued when the manager becomes inactive are discarded.
+    /// While the manager is paused, nothing is dispatched and the events stay queued.
+    pub fn flush(&mut self, state: &mut S) -> usize {
+        if self.paused {
+            return 0;
+        }
+        let queued = core::mem::take(&mut self.queued);
+        self.update_batch(state, queued)
+    }
+
+    /// Like `update`, but only borrows the event, so it can be dispatched to multiple managers.
+    ///
+    /// The event isn't cloned, so it doesn't need to implement `Clone`, like for split screens, where every player has its own stack.
+    pub fn update_ref(&mut self, state: &mut S, event: &E) -> Dispatch {
+        self.guarded(|manager| {
+            if !manager.start_update(state, event) {
+                return Dispatch::Paused;
+            }
+            manager
+                .deliver(state, event, Routing::Consume, |layer, state, event| {
+                    Ok(layer.update(state, event))
+                })
+                .0
+        })
+    }
+
+    /// Like `update`, but lets the event decide how it's delivered to the layers.
+    ///
+    /// Changes are applied in the same way for all routings, and the event filter is applied before the routing is decided.
+    pub fn update_routed(&mut self, state: &mut S, event: E) -> Dispatch
+    where
+        E: EventRouting,
+    {
+        self.guarded(|manager| {
+            if !manager.start_update(state, &event) {
+                return Dispatch::Paused;
+            }
+            let event = match manager.filter_event(state, event) {
+                Some(event) => event,
+                None => return Dispatch::Dropped,
+            };
+            let routing = event.routing();
+            manager
+                .deliver(state, &event, routing, |layer, state, event| {
+                    Ok(layer.update(state, event))
+                })
+                .0
+        })
+    }
+
+    /// Record the event and deliver it to the layers as specified by `routing`, using `handle` for `Routing::Consume`.
+    ///
+    /// Every method recording events goes through here after the event filter, so replaying doesn't need the filter.
+    fn deliver(
+        &mut self,
+        state: &mut S,
+        event: &E,
+        routing: Routing,
+        handle: impl FnMut(&mut dyn Layer<S, E>, &mut S, &E) -> Result<Change<S, E>, LayerError>,
+    ) -> (Dispatch, Result<(), LayerFailure>) {
+        self.record_event(event);
+        match routing {
+            Routing::Consume => self.run_dispatch(state, event, handle),
+            Routing::Broadcast => (self.run_broadcast(state, event), Ok(())),
+            Routing::TopOnly => (self.run_top_only(state, event), Ok(())),
+        }
+    }
+
+    #[cfg(feature = "record")]
+    fn record_event(&mut self, event: &E) {
+        if let Some((events, clone)) = &mut self.recording {
+            events.push(clone(event));
+        }
+    }
+
+    #[cfg(not(feature = "record"))]
+    fn record_event(&mut self, _event: &E) {}
+
+    /// Update only the first layer `update` would ask, and apply its change. The other layers aren't asked at all.
+    fn run_top_only(&mut self, state: &mut S, event: &E) -> Dispatch {
+        let empty = self.layers.is_empty();
+        self.start_dispatch();
+        let first = self.asking_order().into_iter().find(|&i| {
+            let entry = &self.layers[i];
+            !entry.asleep && entry.layer.enabled()
+        });
+        let mut handled = false;
+        if let Some(i) = first {
+            let layer = &mut self.layers[i].layer;
+            match catching(self.catch_panics, || layer.update(state, event)) {
+                Ok(change) => {
+                    handled = change.action != ChangeAction::Pass;
+                    self.apply(i, change, &mut []);
+                }
+                Err(payload) => {
+                    self.last_panic = Some(payload);
+                    self.drop_panicked(i);
+                }
+            }
+        }
+        self.refresh_cover();
+        if !handled {
+            if let Some(handler) = &mut self.on_unhandled {
+                handler(state, event);
+            }
+        }
+        self.passive_pass(state, event);
+        if empty {
+            Dispatch::Empty
+        } else if handled {
+            Dispatch::Consumed
+        } else {
+            Dispatch::PassedThrough
+        }
+    }
+
+    fn start_update(&mut self, state: &mut S, event: &E) -> bool {
+        #[cfg(feature = "std")]
+        self.apply_commands();
+        if self.paused {
+            self.passive_pass(state, event);
+            return false;
+        }
+        true
+    }
+
+    fn run_update(
+        &mut self,
+        state: &mut S,
+        event: E,
+        handle: impl FnMut(&mut dyn Layer<S, E>, &mut S, &E) -> Result<Change<S, E>, LayerError>,
+    ) -> (Dispatch, Result<(), LayerFailure>) {
+        self.guarded(|manager| {
+            if !manager.start_update(state, &event) {
+                return (Dispatch::Paused, Ok(()));
+            }
+            match manager.filter_event(state, event) {
+                Some(event) => manager.deliver(state, &event, Routing::Consume, handle),
+                None => (Dispatch::Dropped, Ok(())),
+            }
+        })
+    }
+
+    fn run_dispatch(
+        &mut self,
+        state: &mut S,
+        event: &E,
+        handle: impl FnMut(&mut dyn Layer<S, E>, &mut S, &E) -> Result<Change<S, E>, LayerError>,
+    ) -> (Dispatch, Result<(), LayerFailure>) {
+        let empty = self.layers.is_empty();
+        let result = self.dispatch(state, event, handle);
+        let aborted = matches!(
+            result,
+            Err(LayerFailure {
+                policy: ErrorPolicy::Abort,
+                ..
+            })
+        );
+        if !aborted {
+            self.passive_pass(state, event);
+        }
+        let dispatch = if empty {
+            Dispatch::Empty
+        } else if let Ok(true) = result {
+            Dispatch::Consumed
+        } else {
+            Dispatch::PassedThrough
+        };
+        (dispatch, result.map(|_| ()))
+    }
+
+    /// Like `update`, but returns the layers removed by the changes instead of dropping them, so the caller can tear them down or reuse them.
+    ///
+    /// This includes layers removing or replacing themselves and all layers removed by clearing the stack.
+    /// The layers are returned in the order they have been removed. When clearing the stack, the top layer comes first.
+    /// `on_detach` has already been called for them, and `on_attach` will be called again when they are added back.
+    pub fn update_taking_removed(&mut self, state: &mut S, event: E) -> Vec<Box<dyn Layer<S, E>>> {
+        self.removed = Some(Vec::new());
+        self.update(state, event);
+        self.removed.take().unwrap_or_default()
+    }
+
+    /// Like `update`, but fails without doing anything if the manager is empty.
+    pub fn update_checked(&mut self, state: &mut S, event: E) -> Result<(), Inactive> {
+        if self.layers.is_empty() {
+            return Err(Inactive);
+        }
+        self.update(state, event);
+        Ok(())
+    }
+
+    /// Like `update`, but calls `Layer::update_output` and returns the outputs of type `O`.
+    ///
+    /// Every layer asked during the dispatch can produce an output, including layers passing the event.
+    /// So the outputs are in the order the layers are asked, the output of the layer consuming the event coming last.
+    /// Outputs of other types are silently discarded, so a layer producing a different type than the caller expects can't be detected here.
+    pub fn update_collect<O: Any>(&mut self, state: &mut S, event: E) -> Vec<O> {
+        let mut outputs = Vec::new();
+        let _ = self.run_update(state, event, |layer, state, event| {
+            let (change, output) = layer.update_output(state, event);
+            if let Some(Ok(output)) = output.map(|output| output.downcast::<O>()) {
+                outputs.push(*output);
+            }
+            Ok(change)
+        });
+        outputs
+    }
+
+    /// Like `update`, but calls `Layer::try_update` and reports the first error to the caller.
+    ///
+    /// What happens to the failing layer and the rest of the dispatch depends on the error policy.
+    /// When the dispatch is aborted, the passive updates are skipped.
+    pub fn try_update(&mut self, state: &mut S, event: E) -> Result<(), LayerFailure> {
+        self.run_update(state, event, |layer, state, event| {
+            layer.try_update(state, event)
+        })
+        .1
+    }
+
+    /// Deliver an event to every layer, regardless of whether the layers above consumed or passed it. Useful for notifications like window resizes.
+    ///
+    /// All layers are updated from top to bottom, skipping sleeping and disabled layers.
+    /// The changes are applied afterwards, starting with the change of the top layer, so changes of layers above never invalidate the layers below.
+    /// When a change clears the stack, the changes of the layers below are discarded.
+    /// Afterwards all layers are updated passively once.
+    ///
+    /// Returns `Dispatch::Consumed` if any layer didn't pass the event.
+    pub fn broadcast(&mut self, state: &mut S, event: &E) -> Dispatch {
+        self.guarded(|manager| manager.run_broadcast(state, event))
+    }
+
+    fn run_broadcast(&mut self, state: &mut S, event: &E) -> Dispatch {
+        if self.paused {
+            self.passive_pass(state, event);
+            return Dispatch::Paused;
+        }
+        let empty = self.layers.is_empty();
+        self.start_dispatch();
+        let mut order = Vec::new();
+        let mut changes = Vec::new();
+        for (i, entry) in self.layers.iter_mut().enumerate().rev() {
+            if entry.asleep || !entry.layer.enabled() {
+                continue;
+            }
+            order.push(i);
+            changes.push(entry.layer.update(state, event));
+        }
+        let mut consumed = false;
+        for (next, change) in changes.into_iter().enumerate() {
+            let clear = change.action == ChangeAction::Clear;
+            consumed |= self
+                .apply(order[next], change, &mut order[next + 1..])
+                .is_some();
+            if clear {
+                break;
+            }
+        }
+        self.refresh_cover();
+        self.passive_pass(state, event);
+        if empty {
+            Dispatch::Empty
+        } else if consumed {
+            Dispatch::Consumed
+        } else {
+            Dispatch::PassedThrough
+        }
+    }
+
+    /// Like `update`, but moves the event into the layer claiming it, using `Layer::take_event` instead of `Layer::update`.
+    ///
+    /// Layers are asked in the same order as in `update`, and opaque layers end the dispatch when using input occlusion.
+    /// The first layer claiming the event ends the dispatch and its change is applied.
+    /// Layers returning the event pass it on by value. If no layer claims it, it's returned to the caller, and the unhandled handler isn't called.
+    /// Since the event is moved, there is no passive update.
+    pub fn update_owned(&mut self, state: &mut S, event: E) -> Option<E> {
+        self.guarded(|manager| {
+            let mut event = event;
+            if manager.paused {
+                return Some(event);
+            }
+            manager.start_dispatch();
+            for i in manager.asking_order() {
+                let entry = &mut manager.layers[i];
+                if entry.asleep || !entry.layer.enabled() {
+                    continue;
+                }
+                match entry.layer.take_event(state, event) {
+                    Ok(change) => {
+                        manager.apply(i, change, &mut []);
+                        manager.refresh_cover();
+                        return None;
+                    }
+                    Err(passed) => event = passed,
+                }
+                if manager.input_occlusion && manager.layers[i].layer.is_opaque() {
+                    break;
+                }
+            }
+            Some(event)
+        })
+    }
+
+    /// Deliver an event only to the layer at `index` and apply its change, regardless of the dispatch order.
+    ///
+    /// The layer is updated even if it's asleep or disabled. There are no passive updates.
+    /// Returns the action of the change, or `None` if there's no layer at `index`.
+    pub fn update_at(&mut self, index: usize, state: &mut S, event: &E) -> Option<ChangeAction> {
+        self.guarded(|manager| {
+            let change = manager.layers.get_mut(index)?.layer.update(state, event);
+            let action = change.action;
+            manager.start_dispatch();
+            manager.apply(index, change, &mut []);
+            manager.refresh_cover();
+            Some(action)
+        })
+    }
+
+    /// Only update all layers passively like at the end of `update`, without dispatching the event to any layer.
+    ///
+    /// The stack isn't changed, so this is useful for rendering on frames without input.
+    /// Layers added by the last update count as newly added only during that update, so they are updated even when using `PassivePolicy::SkipNewlyAdded`.
+    pub fn passive(&mut self, state: &mut S, event: &E) {
+        self.guarded(|manager| {
+            for entry in manager.layers.iter_mut() {
+                entry.fresh = false;
+            }
+            manager.passive_pass(state, event);
+        })
+    }
+
+    /// Apply all changes queued in the `ChangeQueue` returned by `queue` in order, as if the top layer returned them.
+    ///
+    /// When the stack is empty, only the added layers of the changes are used.
+    /// Layers added using `Change::add_and_dispatch` are only added, since there's no event to dispatch.
+    pub fn drain_queue(&mut self, state: &mut S, queue: impl Fn(&mut S) -> &mut ChangeQueue<S, E>) {
+        let changes = core::mem::take(&mut queue(state).changes);
+        for change in changes {
+            match self.layers.len().checked_sub(1) {
+                Some(top) => {
+                    self.apply(top, change, &mut []);
+                }
+                None => {
+                    self.insert_added(0, change.add);
+                }
+            }
+        }
+        self.refresh_cover();
+    }
+
+    /// Find out what the stack would do with an event without changing it.
+    ///
+    /// Layers are asked in the same order and with the same input occlusion as in `update`.
+    /// The action of the first layer, which doesn't pass the event, is returned, or `Pass` if every asked layer passes it.
+    /// Changes returned by the layers are discarded and no passive updates are done.
+    /// The layers still get mutable access to the state, and their effects on the state aren't rolled back.
+    pub fn preview(&mut self, state: &mut S, event: &E) -> ChangeAction {
+        self.guarded(|manager| {
+            for i in manager.asking_order() {
+                let entry = &mut manager.layers[i];
+                if entry.asleep || !entry.layer.enabled() {
+                    continue;
+                }
+                match entry.layer.update(state, event).action {
+                    ChangeAction::Pass => (),
+                    action => return action,
+                }
+                if manager.input_occlusion && entry.layer.is_opaque() {
+                    break;
+                }
+            }
+            ChangeAction::Pass
+        })
+    }
+
+    /// Checks if the stack has changed or a layer marked the manager as dirty by `Change::mark_dirty` since the last call of `take_dirty`.
+    ///
+    /// Updates, in which every layer ignored the event, don't make the manager dirty.
+    pub fn needs_redraw(&self) -> bool {
+        self.dirty
+    }
+
+    /// Like `needs_redraw`, but also resets the dirty flag.
+    pub fn take_dirty(&mut self) -> bool {
+        core::mem::take(&mut self.dirty)
+    }
+
+    /// Catch panics of layers instead of unwinding through the manager. Defaults to false.
+    ///
+    /// A layer panicking in `update` or `passive_update` is removed, and the dispatch continues as if it passed the event, so the stack stays consistent.
+    /// The removed layer is dropped, since it might be in an invalid state. So it's never returned by `update_taking_removed`, and the undo history is cleared.
+    /// The payload of the last panic can be taken by `take_last_panic`.
+    /// Since the state might have been modified partially by the layer, the update is wrapped in `AssertUnwindSafe`, and it's the responsibility of the caller to decide if the state is still usable.
+    ///
+    /// This applies to `update` and the other methods dispatching events to the layers in the usual way, but not to `broadcast`, `update_owned`, `update_at` and `preview`.
+    #[cfg(feature = "std")]
+    pub fn set_catch_panics(&mut self, catch_panics: bool) {
+        self.catch_panics = catch_panics;
+    }
+
+    /// Take the payload of the last panic caught since the last call.
+    #[cfg(feature = "std")]
+    pub fn take_last_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+        self.last_panic.take()
+    }
+
+    /// The id of the layer at `index`.
+    pub fn id_at(&self, index: usize) -> Option<LayerId> {
+        self.layers.get(index).map(|entry| entry.id)
+    }
+
+    /// The current index of the layer with the specified id, if it's still on the stack.
+    pub fn index_of(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|entry| entry.id == id)
+    }
+
+    /// The id of the focused layer.
+    pub fn focus(&self) -> Option<LayerId> {
+        self.focus
+    }
+
+    /// Focus the layer with the specified id.
+    ///
+    /// The focused layer is asked first for every event, even if other layers are above it or have a higher priority.
+    /// If it passes the event, the other layers are asked in the usual order, skipping the focused layer.
+    /// The focus is cleared when the focused layer is removed.
+    pub fn set_focus(&mut self, id: LayerId) {
+        self.focus = Some(id);
+    }
+
+    /// Clear the focus, so events are dispatched in the usual order again.
+    pub fn clear_focus(&mut self) {
+        self.focus = None;
+    }
+
+    /// Checks if the manager is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pause or resume the manager.
+    ///
+    /// While paused, updating the manager does nothing, neither dispatching the event nor updating passively, and `update` returns `Dispatch::Paused`.
+    /// The stack is kept, so updating continues normally after resuming.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.paused_passive = false;
+    }
+
+    /// Pause the manager like `set_paused`, but optionally keep updating passively, so the stack can still be rendered.
+    ///
+    /// When `passive` is true, updating a paused manager only does the passive update, unless the event is moved like in `update_owned`.
+    /// Pausing isn't counted, so pausing the manager again only replaces the `passive` setting, and a single `resume` resumes it.
+    pub fn pause(&mut self, passive: bool) {
+        self.paused = true;
+        self.paused_passive = passive;
+    }
+
+    /// Resume the manager after pausing it.
+    pub fn resume(&mut self) {
+        self.set_paused(false);
+    }
+
+    /// The policy for handling errors in `try_update`.
+    pub fn error_policy(&self) -> ErrorPolicy {
+        self.error_policy
+    }
+
+    /// Set the policy for handling errors in `try_update`.
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.error_policy = policy;
+    }
+
+    /// The policy for choosing the layers updated passively after dispatching an event.
+    pub fn passive_policy(&self) -> PassivePolicy {
+        self.passive_policy
+    }
+
+    /// Set the policy for choosing the layers updated passively after dispatching an event. Defaults to `PassivePolicy::All`.
+    ///
+    /// Skipping newly added layers avoids them being rendered for the event, which created them, before they had a chance to handle any event.
+    pub fn set_passive_policy(&mut self, policy: PassivePolicy) {
+        self.passive_policy = policy;
+    }
+
+    /// The order of the passive updates.
+    pub fn passive_order(&self) -> PassiveOrder {
+        self.passive_order
+    }
+
+    /// Set the order of the passive updates. Defaults to `PassiveOrder::BottomUp`.
+    ///
+    /// The passive updates happen after all changes have been applied, so layers added or removed by the same update follow the same order.
+    pub fn set_passive_order(&mut self, order: PassiveOrder) {
+        self.passive_order = order;
+    }
+
+    /// The order, in which layers of the same priority are asked.
+    pub fn dispatch_order(&self) -> DispatchOrder {
+        self.dispatch_order
+    }
+
+    /// Set the order, in which layers of the same priority are asked. Defaults to `DispatchOrder::TopDown`.
+    ///
+    /// Passing the event continues in this order, and a focused layer is still asked first.
+    /// The changes keep their meaning, so `add` still adds layers directly above the current layer, which are asked next when using `add_and_dispatch`.
+    pub fn set_dispatch_order(&mut self, order: DispatchOrder) {
+        self.dispatch_order = order;
+    }
+
+    /// Checks if input occlusion is enabled.
+    pub fn input_occlusion(&self) -> bool {
+        self.input_occlusion
+    }
+
+    /// Enable or disable input occlusion. Defaults to false.
+    ///
+    /// With input occlusion, an event passed by an opaque layer isn't asked to any further layers, like a modal dialog hiding the stack below it.
+    /// The layers added by the passing layers are still added, and since no layer consumed the event, the unhandled handler is called.
+    /// A focused layer below an opaque layer still receives the event first.
+    pub fn set_input_occlusion(&mut self, input_occlusion: bool) {
+        self.input_occlusion = input_occlusion;
+    }
+
+    /// Set a handler, which is called when an event passes all layers without being consumed, including when the stack is empty.
+    ///
+    /// The handler isn't called when the dispatch is aborted because of an error.
+    pub fn set_unhandled_handler(&mut self, handler: impl FnMut(&mut S, &E) + 'static) {
+        self.on_unhandled = Some(Box::new(handler));
+    }
+
+    /// Set an observer, which is called with the index of the layer and the action for every change returned by a layer during `update`, before the change is applied.
+    pub fn set_observer(&mut self, observer: Observer) {
+        self.observer = Some(observer);
+    }
+
+    /// Set a coalescer, which merges consecutive events in `update_batch`, for example to only dispatch the latest of many pointer moves.
+    ///
+    /// The coalescer receives the pending event and the next event, and decides what happens with them. The first event is always kept pending.
+    /// Other methods dispatch every event unchanged. Use `queue` and `set_coalesce` for events arriving one by one.
+    pub fn set_coalescer(&mut self, coalescer: Coalescer<E>) {
+        self.coalescer = Some(coalescer);
+    }
+
+    /// Set an observer, which is notified about every layer added to or removed from the stack.
+    pub fn set_stack_observer(&mut self, observer: Box<dyn StackObserver<S, E>>) {
+        self.stack_observer = Some(observer);
+    }
+
+    /// Set a filter, which preprocesses every event before it's dispatched, for example to remap keys or scale coordinates.
+    ///
+    /// The filter runs before any layer is asked, so it sees the events in the order they are passed to the manager.
+    /// Returning `None` drops the event, so neither the layers nor the passive updates see it, and `update` returns `Dispatch::Dropped`.
+    /// Returning `Some` dispatches the returned event instead.
+    ///
+    /// The filter is applied by `update`, `update_taking_removed`, `update_checked`, `update_collect` and `try_update`.
+    /// Methods only borrowing the event, like `update_ref`, and `update_owned` dispatch the event unchanged.
+    /// While the manager is paused, the filter isn't called.
+    pub fn set_event_filter(&mut self, filter: EventFilter<S, E>) {
+        self.event_filter = Some(filter);
+    }
+
+    fn filter_event(&mut self, state: &mut S, event: E) -> Option<E> {
+        if self.paused {
+            return None;
+        }
+        match &mut self.event_filter {
+            Some(filter) => filter(state, event),
+            None => Some(event),
+        }
+    }
+
+    fn dispatch(
+        &mut self,
+        state: &mut S,
+        event: &E,
+        mut handle: impl FnMut(&mut dyn Layer<S, E>, &mut S, &E) -> Result<Change<S, E>, LayerError>,
+    ) -> Result<bool, LayerFailure> {
+        if self.paused {
+            return Ok(false);
+        }
+        self.start_dispatch();
+        let catch_panics = self.catch_panics;
+        let input_occlusion = self.input_occlusion;
+        let mut result = Ok(());
+        let mut handled = false;
+        let mut order = self.asking_order();
+        for round in 0..=Self::MAX_REDISPATCH {
+            let mut aborted = false;
+            let mut positions = Vec::new();
+            let mut pending = Vec::new();
+            for i in core::mem::take(&mut order) {
+                let entry = &mut self.layers[i];
+                if entry.asleep || !entry.layer.enabled() {
+                    continue;
+                }
+                let layer = &mut *entry.layer;
+                let outcome = match catching(catch_panics, || handle(layer, state, event)) {
+                    Ok(outcome) => outcome,
+                    Err(payload) => {
+                        self.last_panic = Some(payload);
+                        positions.push(i);
+                        pending.push(Asked::Panicked);
+                        continue;
+                    }
+                };
+                match outcome {
+                    Ok(change) => {
+                        let consumed = change.action != ChangeAction::Pass;
+                        positions.push(i);
+                        pending.push(Asked::Changed(change));
+                        if consumed {
+                            handled = true;
+                            break;
+                        }
+                        if input_occlusion && self.layers[i].layer.is_opaque() {
+                            break;
+                        }
+                    }
+                    Err(error) => {
+                        let policy = self.error_policy;
+                        if result.is_ok() {
+                            result = Err(LayerFailure {
+                                index: i,
+                                name: entry.layer.name(),
+                                policy,
+                                error,
+                            });
+                        }
+                        match policy {
+                            ErrorPolicy::Abort => {
+                                aborted = true;
+                                break;
+                            }
+                            ErrorPolicy::Skip => (),
+                            ErrorPolicy::Remove => {
+                                positions.push(i);
+                                pending.push(Asked::Failed);
+                            }
+                        }
+                    }
+                }
+            }
+            let mut redispatch = None;
+            for (next, change) in pending.into_iter().enumerate() {
+                let (i, rest) = positions[next..].split_first_mut().unwrap();
+                match change {
+                    Asked::Changed(change) => {
+                        let dispatching = matches!(change.placement, Placement::Dispatched);
+                        let added = self.apply(*i, change, rest);
+                        if dispatching {
+                            redispatch = added;
+                        }
+                    }
+                    removed => {
+                        match removed {
+                            Asked::Panicked => self.drop_panicked(*i),
+                            _ => self.discard(*i),
+                        }
+                        for index in rest {
+                            if *index > *i {
+                                *index -= 1;
+                            }
+                        }
+                    }
+                }
+            }
+            if aborted {
+                self.refresh_cover();
+                return result.map(|()| false);
+            }
+            match redispatch {
+                Some(added) if round < Self::MAX_REDISPATCH => order = added.rev().collect(),
+                Some(_) => {
+                    debug!("stopped dispatching to added layers after {} rounds", round);
+                }
+                None => break,
+            }
+        }
+        self.refresh_cover();
+        if !handled {
+            if let Some(handler) = &mut self.on_unhandled {
+                handler(state, event);
+            }
+        }
+        result.map(|()| handled)
+    }
+
+    fn apply(
+        &mut self,
+        i: usize,
+        change: Change<S, E>,
+        order: &mut [usize],
+    ) -> Option<Range<usize>> {
+        let Change {
+            add,
+            action,
+            placement,
+            dirty,
+        } = change;
+        self.dirty |= dirty;
+        let name = self.layers[i].layer.name();
+        if let Some(observer) = &mut self.observer {
+            observer(i, &action);
+        }
+        if action == ChangeAction::Clear {
+            self.clear_layers();
+        }
+        let add_index = match (action, placement) {
+            (ChangeAction::Clear, _) => self.layers.len(),
+            (_, Placement::Above | Placement::Dispatched) => i + 1,
+            (_, Placement::Bottom) => 0,
+            (_, Placement::Offset(offset)) => {
+                let index = (i as isize).saturating_add(offset).saturating_add(1);
+                index.clamp(0, self.layers.len() as isize) as usize
+            }
+        };
+        let dropped = self.dropped;
+        let added = self.insert_added(add_index, add);
+        if self.dropped > dropped {
+            self.exceeded(i, name, self.dropped - dropped);
+        }
+        for entry in &mut self.layers[add_index..add_index + added] {
+            entry.fresh = true;
+        }
+        for index in order.iter_mut() {
+            if *index >= add_index {
+                *index += added;
+            }
+        }
+        let i = if add_index <= i { i + added } else { i };
+        let mut added = add_index..add_index + added;
+        use ChangeAction::*;
+        match action {
+            None => (),
+            Pass => return Option::None,
+            Remove => {
+                if !self.layers[i].layer.sticky() {
+                    self.retire(i);
+                    for index in order.iter_mut() {
+                        if *index > i {
+                            *index -= 1;
+                        }
+                    }
+                    if i < added.start {
+                        added = added.start - 1..added.end - 1;
+                    }
+                }
+            }
+            Clear => (),
+            Sleep => self.layers[i].asleep = true,
+            Wake => {
+                for entry in self.layers.iter_mut() {
+                    entry.asleep = false;
+                }
+            }
+        }
+        Some(added)
+    }
+
+    fn refresh_cover(&mut self) {
+        if self.editing {
+            return;
+        }
+        self.commit_history();
+        if let Some((top, below)) = self.layers.split_last_mut() {
+            for entry in below {
+                if !entry.covered {
+                    entry.covered = true;
+                    entry.layer.on_cover();
+                }
+            }
+            if top.covered {
+                top.covered = false;
+                top.layer.on_uncover();
+            }
+        }
+    }
+
+    fn guarded<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        assert!(
+            !self.updating,
+            "a layer manager can't be updated by its own layers; return a change or use a `ChangeQueue` instead"
+        );
+        self.updating = true;
+        let guard = Updating(self);
+        f(&mut *guard.0)
+    }
+
+    fn batch<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let editing = core::mem::replace(&mut self.editing, true);
+        let result = f(self);
+        self.editing = editing;
+        self.refresh_cover();
+        result
+    }
+
+    fn start_dispatch(&mut self) {
+        self.dropped = 0;
+        self.last_error = None;
+        for entry in self.layers.iter_mut() {
+            entry.fresh = false;
+        }
+    }
+
+    fn exceeded(&mut self, index: usize, name: &'static str, dropped: usize) {
+        if self.depth_policy == DepthPolicy::Drop {
+            return;
+        }
+        if self.depth_policy == DepthPolicy::Panic && cfg!(debug_assertions) {
+            panic!(
+                "layer {} at {} exceeded the depth limit by {} layers",
+                name, index, dropped
+            );
+        }
+        self.last_error = Some(DepthExceeded {
+            index,
+            name,
+            dropped,
+        });
+    }
+
+    fn insert_added(&mut self, index: usize, add: Vec<Box<dyn Layer<S, E>>>) -> usize {
+        let room = match self.max_depth {
+            Some(max_depth) => max_depth.saturating_sub(self.layers.len()),
+            None => add.len(),
+        };
+        let count = add.len().min(room);
+        self.dropped += add.len() - count;
+        let first = self.next_id;
+        self.next_id += count as u64;
+        self.layers.splice(
+            index..index,
+            add.into_iter()
+                .take(count)
+                .zip(first..)
+                .map(|(layer, id)| Entry::new(layer, LayerId(id))),
+        );
+        if count > 0 {
+            self.record(Edit::Inserted { index, count });
+            self.notify_added(index..index + count);
+            self.dirty = true;
+            debug!(
+                "added {} layers at {}, depth {}",
+                count,
+                index,
+                self.layers.len()
+            );
+        }
+        count
+    }
+
+    fn next_id(&mut self) -> LayerId {
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn remove_layer(&mut self, index: usize) -> Box<dyn Layer<S, E>> {
+        let Entry { mut layer, id, .. } = self.layers.remove(index);
+        self.dirty = true;
+        if self.focus == Some(id) {
+            self.focus = None;
+        }
+        layer.on_detach();
+        if let Some(observer) = &mut self.stack_observer {
+            observer.on_removed(index, &*layer);
+        }
+        debug!("removed layer at {}, depth {}", index, self.layers.len());
+        layer
+    }
+
+    fn notify_added(&mut self, added: Range<usize>) {
+        if let Some(observer) = &mut self.stack_observer {
+            for index in added {
+                observer.on_added(index, &*self.layers[index].layer);
+            }
+        }
+    }
+
+    fn clear_layers(&mut self) {
+        debug!("cleared {} layers", self.layers.len());
+        let mut count = 0;
+        for index in (0..self.layers.len()).rev() {
+            let layer = &self.layers[index].layer;
+            if !layer.sticky() && !layer.is_persistent() {
+                self.retire(index);
+                count += 1;
+            }
+        }
+        if let Some(observer) = &mut self.stack_observer {
+            observer.on_cleared(count);
+        }
+    }
+
+    fn discard(&mut self, index: usize) {
+        let layer = self.remove_layer(index);
+        self.record(Edit::Removed {
+            index,
+            layers: alloc::vec![layer],
+        });
+    }
+
+    fn retire(&mut self, index: usize) {
+        if self.removed.is_none() {
+            return self.discard(index);
+        }
+        let layer = self.remove_layer(index);
+        self.lose_history();
+        if let Some(removed) = &mut self.removed {
+            removed.push(layer);
+        }
+    }
+
+    fn drop_panicked(&mut self, index: usize) {
+        self.remove_layer(index);
+        self.lose_history();
+    }
+
+    fn asking_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = match self.dispatch_order {
+            DispatchOrder::TopDown => (0..self.layers.len()).rev().collect(),
+            DispatchOrder::BottomUp => (0..self.layers.len()).collect(),
+        };
+        order.sort_by_key(|&i| core::cmp::Reverse(self.layers[i].layer.priority()));
+        if let Some(focused) = self.focus.and_then(|id| self.index_of(id)) {
+            if let Some(position) = order.iter().position(|&i| i == focused) {
+                order[..=position].rotate_right(1);
+            }
+        }
+        order
+    }
+
+    fn passive_pass(&mut self, state: &mut S, event: &E) {
+        if self.paused && !self.paused_passive {
+            return;
+        }
+        let skip_fresh = self.passive_policy == PassivePolicy::SkipNewlyAdded;
+        let catch_panics = self.catch_panics;
+        let mut panicked = Vec::new();
+        let mut last_panic = None;
+        let update = |(index, entry): (usize, &mut Entry<S, E>)| {
+            if skip_fresh && entry.fresh {
+                return;
+            }
+            if entry.layer.enabled() && entry.layer.needs_passive() {
+                let layer = &mut entry.layer;
+                if let Err(payload) = catching(catch_panics, || layer.passive_update(state, event))
+                {
+                    panicked.push(index);
+                    last_panic = Some(payload);
+                }
+            }
+        };
+        match self.passive_order {
+            PassiveOrder::BottomUp => self.layers.iter_mut().enumerate().for_each(update),
+            PassiveOrder::TopDown => self.layers.iter_mut().enumerate().rev().for_each(update),
+        }
+        if last_panic.is_some() {
+            self.last_panic = last_panic;
+            panicked.sort_unstable();
+            for index in panicked.into_iter().rev() {
+                self.drop_panicked(index);
+            }
+            self.refresh_cover();
+        }
+    }
+}
+
+/// A layer manager can be used as a layer of another layer manager, so a whole stack can be handled as a single layer.
+///
+/// Events are dispatched to the inner stack and passed, when no inner layer consumed them.
+/// The layer removes itself, as soon as the inner stack is empty.
+impl<S, E, A: Allocator> Layer<S, E> for LayerManager<S, E, A> {
+    fn passive_update(&mut self, state: &mut S, event: &E) {
+        self.guarded(|manager| manager.passive_pass(state, event));
+    }
+
+    fn needs_passive(&self) -> bool {
+        (!self.paused || self.paused_passive)
+            && self
+                .iter()
+                .any(|layer| layer.enabled() && layer.needs_passive())
+    }
+
+    fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+        let handled = self.guarded(|manager| {
+            if !manager.paused {
+                manager.record_event(event);
+            }
+            matches!(
+                manager.dispatch(state, event, |layer, state, event| {
+                    Ok(layer.update(state, event))
+                }),
+                Ok(true)
+            )
+        });
+        if !self.is_active() {
+            Change::remove()
+        } else if handled {
+            Change::none()
+        } else {
+            Change::pass()
+        }
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S, E, A: Allocator> LayerManager<S, E, A> {
+    /// Stop recording and return the recorded events, or an empty tape if it wasn't recording.
+    pub fn stop_recording(&mut self) -> EventTape<E> {
+        EventTape(
+            self.recording
+                .take()
+                .map(|(events, _)| events)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Like `stop_recording`, but returns the list of events directly.
+    pub fn take_recording(&mut self) -> Vec<E> {
+        self.stop_recording().0
+    }
+
+    /// Checks if the manager is recording events.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Dispatch the recorded events in order like `update`, and return the number of dispatched events.
+    ///
+    /// The events are recorded after the event filter, so they are dispatched directly, without applying the event filter or the coalescer again.
+    /// Every event is dispatched like in `update`. Events recorded by `update_routed` have to be replayed by `replay_routed` to keep their routing.
+    /// Unlike `from_recording`, this keeps the current stack, for example a stack rebuilt by the caller. It stops early when the manager becomes inactive.
+    pub fn replay(&mut self, state: &mut S, tape: &EventTape<E>) -> usize {
+        self.replay_with(state, tape, |_| Routing::Consume)
+    }
+
+    /// Like `replay`, but delivers each event as specified by its routing, like `update_routed`.
+    pub fn replay_routed(&mut self, state: &mut S, tape: &EventTape<E>) -> usize
+    where
+        E: EventRouting,
+    {
+        self.replay_with(state, tape, E::routing)
+    }
+
+    fn replay_with(
+        &mut self,
+        state: &mut S,
+        tape: &EventTape<E>,
+        routing: impl Fn(&E) -> Routing,
+    ) -> usize {
+        let mut count = 0;
+        for event in &tape.0 {
+            if !self.is_active() {
+                break;
+            }
+            self.guarded(|manager| {
+                if manager.start_update(state, event) {
+                    let _ = manager.deliver(state, event, routing(event), |layer, state, event| {
+                        Ok(layer.update(state, event))
+                    });
+                }
+            });
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S, E> LayerManager<S, E> {
+    /// Create a manager from the initial layers, and update it with the events in order.
+    ///
+    /// Replaying recorded events on the same initial layers and state reproduces the recorded session, as long as the layers are deterministic.
+    pub fn from_recording(
+        layers: Vec<Box<dyn Layer<S, E>>>,
+        events: impl IntoIterator<Item = E>,
+        state: &mut S,
+    ) -> Self {
+        let mut manager = Self::new(layers);
+        for event in events {
+            manager.update(state, event);
+        }
+        manager
+    }
+}
+
+#[cfg(feature = "record")]
+impl<S, E: Clone, A: Allocator> LayerManager<S, E, A> {
+    /// Start recording all events dispatched to the layers, discarding any previous recording.
+    ///
+    /// Recording requires `E: Clone`, since the manager has to keep a copy of the events. Events received while paused aren't recorded.
+    /// The events are recorded as they are dispatched, after the event filter and the coalescer, by `update`, the methods based on it, `update_ref` and `update_routed`.
+    /// Events delivered by `broadcast`, `update_at` or `update_owned` aren't recorded, since replaying them like `update` would change the session.
+    pub fn start_recording(&mut self) {
+        self.recording = Some((Vec::new(), E::clone));
+    }
+}
+
+impl<S, E> Default for LayerManager<S, E> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use alloc::vec;
+
+    #[derive(Clone)]
+    pub enum Event {
+        Idle,
+        Input,
+        Exit,
+    }
+
+    pub struct GlobalState;
+
+    pub struct MainLayer;
+
+    impl Layer<GlobalState, Event> for MainLayer {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            match event {
+                Event::Input => Change::add_one(TopLayer),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    pub struct TopLayer;
+
+    impl Layer<GlobalState, Event> for TopLayer {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            match event {
+                Event::Input => Change::pass(),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    pub struct Reported;
+
+    /// Handles every event with an output, while every fallible update fails. Used to check adapters forward both.
+    pub struct Reporter;
+
+    impl Layer<GlobalState, Event> for Reporter {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+
+        fn update_output(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> (Change<GlobalState, Event>, Option<Box<dyn Any>>) {
+            (Change::none(), Some(Box::new(Reported)))
+        }
+
+        fn try_update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Result<Change<GlobalState, Event>, LayerError> {
+            Err(LayerError::new(Reported))
+        }
+    }
+
+    pub fn reports_output(layer: &mut dyn Layer<GlobalState, Event>) -> bool {
+        let (_, output) = layer.update_output(&mut GlobalState, &Event::Input);
+        output.is_some_and(|output| output.is::<Reported>())
+    }
+
+    pub fn reports_error(layer: &mut dyn Layer<GlobalState, Event>) -> bool {
+        match layer.try_update(&mut GlobalState, &Event::Input) {
+            Ok(_) => false,
+            Err(error) => error.is::<Reported>(),
+        }
+    }
+
+    #[test]
+    fn example() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Idle);
+
+        while manager.is_active() {
+            manager.update(&mut state, Event::Exit);
+        }
+    }
+
+    #[test]
+    fn default() {
+        let manager = LayerManager::<GlobalState, Event>::default();
+        assert!(!manager.is_active());
+        assert_eq!(manager.len(), 0);
+    }
+
+    #[test]
+    fn preview() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        assert_eq!(
+            manager.preview(&mut state, &Event::Input),
+            ChangeAction::None
+        );
+        assert_eq!(manager.len(), 2);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+
+        assert_eq!(
+            manager.preview(&mut state, &Event::Exit),
+            ChangeAction::Remove
+        );
+        assert_eq!(manager.len(), 3);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 2);
+
+        let passing = testing::RecordingLayer::new("passing", &Default::default(), summary)
+            .with_script(vec![Change::pass()]);
+        let mut passing = LayerManager::new(vec![Box::new(passing)]);
+        assert_eq!(
+            passing.preview(&mut state, &Event::Idle),
+            ChangeAction::Pass
+        );
+
+        struct Modal;
+
+        impl Layer<GlobalState, Event> for Modal {
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                _event: &Event,
+            ) -> Change<GlobalState, Event> {
+                Change::pass()
+            }
+
+            fn is_opaque(&self) -> bool {
+                true
+            }
+        }
+
+        let mut occluded = LayerManager::new(vec![Box::new(MainLayer), Box::new(Modal)]);
+        occluded.set_input_occlusion(true);
+        assert_eq!(
+            occluded.preview(&mut state, &Event::Idle),
+            ChangeAction::Pass
+        );
+        assert_eq!(
+            occluded.update(&mut state, Event::Idle),
+            Dispatch::PassedThrough
+        );
+        occluded.set_input_occlusion(false);
+        assert_eq!(
+            occluded.preview(&mut state, &Event::Idle),
+            ChangeAction::None
+        );
+    }
+
+    #[test]
+    fn coalescer() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager =
+            LayerManager::from_layer(Box::new(RecordingLayer::new("layer", &log, summary)));
+        let mut state = GlobalState;
+        let events = || {
+            [
+                Event::Idle,
+                Event::Idle,
+                Event::Idle,
+                Event::Input,
+                Event::Idle,
+                Event::Idle,
+                Event::Input,
+                Event::Input,
+                Event::Exit,
+            ]
+        };
+        let updates = || {
+            let updates = calls(&log, None, CallKind::Update)
+                .iter()
+                .map(|call| call.event)
+                .collect::<Vec<_>>();
+            log.borrow_mut().clear();
+            updates
+        };
+
+        assert_eq!(manager.update_batch(&mut state, events()), 9);
+        assert_eq!(updates().len(), 9);
+
+        manager.set_coalescer(Box::new(|pending, event| match (&*pending, event) {
+            (Event::Idle, Event::Idle) => CoalesceDecision::Merge,
+            (Event::Input, Event::Input) => CoalesceDecision::DispatchBoth(Event::Input),
+            (_, event) => CoalesceDecision::Flush(event),
+        }));
+        assert_eq!(manager.update_batch(&mut state, events()), 6);
+        assert_eq!(
+            updates()[..],
+            ["idle", "input", "idle", "input", "input", "exit"]
+        );
+        assert_eq!(manager.update_batch(&mut state, [Event::Idle]), 1);
+        assert_eq!(updates()[..], ["idle"]);
+    }
+
+    #[test]
+    fn update_batch() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        let mut events = vec![Event::Idle, Event::Exit, Event::Exit, Event::Input].into_iter();
+        assert_eq!(manager.update_batch(&mut state, &mut events), 3);
+        assert!(!manager.is_active());
+        assert!(matches!(events.next(), Some(Event::Input)));
+
+        assert_eq!(manager.update_batch(&mut state, vec![Event::Idle]), 0);
+    }
+
+    #[test]
+    fn update_with() {
+        let mut manager = LayerManager::from_layer(Box::new(MainLayer));
+        let mut state = GlobalState;
+
+        let len = manager.update_with(&mut state, Event::Input, |manager, _| manager.len());
+        assert_eq!(len, 2);
+        let top = manager.update_with(&mut state, Event::Input, |manager, _| {
+            manager.iter().last().map(|layer| layer.name())
+        });
+        assert_eq!(top, Some(core::any::type_name::<TopLayer>()));
+    }
+
+    #[test]
+    fn capacity() {
+        let mut manager = LayerManager::from_layer(Box::new(MainLayer));
+        manager.reserve(32);
+        assert!(manager.capacity() >= 33);
+
+        for _ in 0..16 {
+            manager.push(Box::new(TopLayer));
+        }
+        manager.truncate(1);
+        manager.shrink_to_fit();
+        assert!(manager.capacity() < 32);
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn update_at() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        assert_eq!(
+            manager.update_at(0, &mut state, &Event::Input),
+            Some(ChangeAction::None)
+        );
+        assert_eq!(manager.len(), 3);
+        assert!(manager.iter().nth(1).unwrap().name().ends_with("TopLayer"));
+
+        assert_eq!(
+            manager.update_at(1, &mut state, &Event::Input),
+            Some(ChangeAction::Pass)
+        );
+        assert_eq!(manager.len(), 3);
+
+        assert_eq!(
+            manager.update_at(0, &mut state, &Event::Exit),
+            Some(ChangeAction::Remove)
+        );
+        assert_eq!(manager.len(), 2);
+        assert_eq!(manager.update_at(2, &mut state, &Event::Idle), None);
+    }
+
+    #[test]
+    fn run() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        let mut pulled = 0;
+        let mut source = |_: &mut GlobalState| {
+            pulled += 1;
+            Some(Event::Exit)
+        };
+        assert_eq!(manager.run(&mut state, &mut source), StopReason::Inactive);
+        assert_eq!(pulled, 2);
+
+        let mut manager = LayerManager::from_layer(Box::new(MainLayer));
+        let mut events = vec![Event::Input, Event::Idle].into_iter();
+        assert_eq!(
+            manager.run(&mut state, |_| events.next()),
+            StopReason::SourceExhausted
+        );
+        assert_eq!(manager.len(), 2);
+    }
+
+    struct Reentry {
+        manager: *mut LayerManager<Reentry, Event>,
+    }
+
+    struct Reentering;
+
+    impl Layer<Reentry, Event> for Reentering {
+        fn update(&mut self, state: &mut Reentry, _event: &Event) -> Change<Reentry, Event> {
+            let manager = state.manager;
+            unsafe { (*manager).update(state, Event::Idle) };
+            Change::none()
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be updated by its own layers")]
+    fn reentrant_update() {
+        let mut manager = LayerManager::from_layer(Box::new(Reentering));
+        let mut state = Reentry {
+            manager: &mut manager,
+        };
+
+        manager.update(&mut state, Event::Input);
+    }
+
+    #[test]
+    fn sequential_updates() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.broadcast(&mut state, &Event::Idle);
+        manager.update_at(0, &mut state, &Event::Idle);
+        assert!(manager.try_update(&mut state, Event::Idle).is_ok());
+        assert_eq!(manager.len(), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn update_after_panic() {
+        struct Crashing;
+
+        impl Layer<GlobalState, Event> for Crashing {
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                event: &Event,
+            ) -> Change<GlobalState, Event> {
+                match event {
+                    Event::Input => panic!("crash"),
+                    _ => Change::none(),
+                }
+            }
+        }
+
+        let mut manager = LayerManager::new(vec![Box::new(Crashing)]);
+        let mut state = GlobalState;
+
+        let crashed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            manager.update(&mut state, Event::Input)
+        }));
+        assert!(crashed.is_err());
+        manager.update(&mut state, Event::Idle);
+        assert!(manager.try_update(&mut state, Event::Idle).is_ok());
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn add_maybe() {
+        use alloc::format;
+
+        let debug = |change: Change<GlobalState, Event>| format!("{:?}", change);
+        let some = Some(Box::new(TopLayer) as Box<dyn Layer<GlobalState, Event>>);
+        assert_eq!(
+            debug(Change::add_maybe(some)),
+            debug(Change::add_one(TopLayer))
+        );
+        assert_eq!(debug(Change::add_maybe(None)), debug(Change::none()));
+        assert_eq!(
+            debug(Change::push_if(true, TopLayer)),
+            debug(Change::add_one(TopLayer))
+        );
+        assert_eq!(
+            debug(Change::push_if(false, TopLayer)),
+            debug(Change::none())
+        );
+    }
+
+    #[test]
+    fn change_debug() {
+        use alloc::format;
+
+        let change = Change::<GlobalState, Event>::add([
+            Box::new(MainLayer) as Box<dyn Layer<GlobalState, Event>>,
+            Box::new(TopLayer),
+        ])
+        .removing();
+        assert_eq!(
+            format!("{:?}", change),
+            "Change { action: Remove, add: 2 layers }"
+        );
+        assert_eq!(
+            format!("{:?}", Change::<GlobalState, Event>::pass()),
+            "Change { action: Pass, add: 0 layers }"
+        );
+    }
+
+    #[test]
+    fn add_at_offset() {
+        let tags = |offset| {
+            let mut manager = LayerManager::new(vec![
+                Box::new(Tagged(1)),
+                Box::new(Scripted(Some(Change::add_at_offset(
+                    offset,
+                    [Box::new(Tagged(9)) as Box<dyn Layer<GlobalState, Event>>],
+                )))),
+                Box::new(Tagged(3)),
+            ]);
+            manager.set_asleep(2, true);
+            manager.update(&mut GlobalState, Event::Idle);
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(tags(-1)[..], [1, 9, 0, 3]);
+        assert_eq!(tags(0)[..], [1, 0, 9, 3]);
+        assert_eq!(tags(1)[..], [1, 0, 3, 9]);
+        assert_eq!(tags(-2)[..], [9, 1, 0, 3]);
+        assert_eq!(tags(-5)[..], [9, 1, 0, 3]);
+        assert_eq!(tags(5)[..], [1, 0, 3, 9]);
+        assert_eq!(tags(isize::MIN)[..], [9, 1, 0, 3]);
+        assert_eq!(tags(isize::MAX)[..], [1, 0, 3, 9]);
+    }
+
+    #[test]
+    fn drain_queue() {
+        #[derive(Default)]
+        struct Requests {
+            queue: ChangeQueue<Requests, Event>,
+        }
+
+        struct Requester;
+
+        impl Layer<Requests, Event> for Requester {
+            fn update(&mut self, state: &mut Requests, event: &Event) -> Change<Requests, Event> {
+                match event {
+                    Event::Input => state.queue.push(Change::add_one(Requester)),
+                    Event::Exit => state.queue.push(Change::remove()),
+                    Event::Idle => (),
+                }
+                Change::none()
+            }
+        }
+
+        let mut manager = LayerManager::from_layer(Box::new(Requester));
+        let mut state = Requests::default();
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 1);
+        assert_eq!(state.queue.len(), 1);
+        manager.drain_queue(&mut state, |state| &mut state.queue);
+        assert_eq!(manager.len(), 2);
+        assert!(state.queue.is_empty());
+
+        manager.update(&mut state, Event::Exit);
+        manager.drain_queue(&mut state, |state| &mut state.queue);
+        assert_eq!(manager.len(), 1);
+        manager.update(&mut state, Event::Exit);
+        manager.drain_queue(&mut state, |state| &mut state.queue);
+        assert!(manager.is_empty());
+
+        state.queue.push(Change::add_one(Requester));
+        manager.drain_queue(&mut state, |state| &mut state.queue);
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn passive() {
+        use testing::{CallKind, CallLog, CallRecord, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(RecordingLayer::new("bottom", &log, summary)),
+            Box::new(RecordingLayer::new("top", &log, summary).with_script([Change::remove()])),
+        ]);
+        let mut state = GlobalState;
+
+        manager.passive(&mut state, &Event::Exit);
+        assert_eq!(manager.len(), 2);
+        assert_eq!(
+            log.borrow()[..],
+            [
+                CallRecord {
+                    layer: "bottom",
+                    kind: CallKind::Passive,
+                    event: "exit",
+                },
+                CallRecord {
+                    layer: "top",
+                    kind: CallKind::Passive,
+                    event: "exit",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flush() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager =
+            LayerManager::from_layer(Box::new(RecordingLayer::new("layer", &log, summary)));
+        let mut state = GlobalState;
+        let updates = || {
+            let updates = calls(&log, None, CallKind::Update)
+                .iter()
+                .map(|call| call.event)
+                .collect::<Vec<_>>();
+            log.borrow_mut().clear();
+            updates
+        };
+
+        manager.queue(Event::Idle);
+        manager.queue(Event::Input);
+        manager.queue(Event::Idle);
+        assert!(updates().is_empty());
+        assert_eq!(manager.flush(&mut state), 1);
+        assert_eq!(updates()[..], ["idle"]);
+        assert_eq!(manager.flush(&mut state), 0);
+
+        manager.set_coalesce(|last, event| matches!((last, event), (Event::Idle, Event::Idle)));
+        for event in [
+            Event::Idle,
+            Event::Idle,
+            Event::Input,
+            Event::Idle,
+            Event::Exit,
+        ] {
+            manager.queue(event);
+        }
+        assert_eq!(manager.flush(&mut state), 4);
+        assert_eq!(updates()[..], ["idle", "input", "idle", "exit"]);
+    }
+
+    #[test]
+    fn from_layer() {
+        let mut manager = LayerManager::from_layer(Box::new(MainLayer));
+        assert_eq!(manager.len(), 1);
+
+        manager.update(&mut GlobalState, Event::Exit);
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn max_depth() {
+        let mut manager = LayerManager::with_max_depth(vec![Box::new(MainLayer)], 3);
+        let mut state = GlobalState;
+        assert_eq!(manager.max_depth(), Some(3));
+
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Input);
+        assert_eq!((manager.len(), manager.dropped()), (3, 0));
+        manager.update(&mut state, Event::Input);
+        assert_eq!((manager.len(), manager.dropped()), (3, 1));
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(manager.dropped(), 0);
+    }
+
+    pub struct Runaway;
+
+    impl Layer<GlobalState, Event> for Runaway {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::add([
+                Box::new(MainLayer) as Box<dyn Layer<GlobalState, Event>>,
+                Box::new(MainLayer),
+            ])
+        }
+    }
+
+    #[test]
+    fn depth_policy() {
+        let run = |policy| {
+            let mut manager = LayerManager::new(vec![Box::new(TopLayer), Box::new(Runaway)]);
+            manager.set_max_depth(Some(3));
+            manager.set_depth_policy(policy);
+            manager.update(&mut GlobalState, Event::Idle);
+            assert_eq!((manager.len(), manager.dropped()), (3, 1));
+            manager.last_error()
+        };
+
+        assert_eq!(run(DepthPolicy::Drop), None);
+        assert_eq!(
+            run(DepthPolicy::Report),
+            Some(DepthExceeded {
+                index: 1,
+                name: core::any::type_name::<Runaway>(),
+                dropped: 1,
+            })
+        );
+
+        let mut manager = LayerManager::from_layer(Box::new(Runaway));
+        manager.set_depth_policy(DepthPolicy::Report);
+        manager.update(&mut GlobalState, Event::Idle);
+        assert_eq!((manager.len(), manager.last_error()), (3, None));
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeded the depth limit")]
+    fn depth_policy_panic() {
+        let mut manager = LayerManager::with_max_depth(vec![Box::new(Runaway)], 2);
+        manager.set_depth_policy(DepthPolicy::Panic);
+        manager.update(&mut GlobalState, Event::Idle);
+    }
+
+    #[test]
+    fn update_ref() {
+        let mut first = LayerManager::new(vec![Box::new(MainLayer)]);
+        let mut second = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        let event = Event::Input;
+        first.update_ref(&mut state, &event);
+        second.update_ref(&mut state, &event);
+        assert_eq!((first.len(), second.len()), (2, 3));
+
+        let event = Event::Exit;
+        for manager in [&mut first, &mut second] {
+            manager.update_ref(&mut state, &event);
+        }
+        assert_eq!((first.len(), second.len()), (1, 2));
+    }
+
+    #[test]
+    fn active_index() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        assert_eq!(manager.active_index(), Some(manager.len() - 1));
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.active_index(), Some(manager.len() - 1));
+
+        while manager.is_active() {
+            manager.update(&mut state, Event::Exit);
+        }
+        assert_eq!(manager.active_index(), None);
+    }
+
+    fn summary(event: &Event) -> &'static str {
+        match event {
+            Event::Idle => "idle",
+            Event::Input => "input",
+            Event::Exit => "exit",
+        }
+    }
+
+    #[test]
+    fn event_filter() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager =
+            LayerManager::from_layer(Box::new(RecordingLayer::new("layer", &log, summary)));
+        manager.set_event_filter(Box::new(|_, event| match event {
+            Event::Idle => None,
+            Event::Exit => Some(Event::Input),
+            event => Some(event),
+        }));
+        let mut state = GlobalState;
+
+        assert_eq!(manager.update(&mut state, Event::Idle), Dispatch::Dropped);
+        assert_eq!(manager.update(&mut state, Event::Exit), Dispatch::Consumed);
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Input);
+
+        assert_eq!(log.borrow().len(), 4);
+        let updates = calls(&log, None, CallKind::Update)
+            .iter()
+            .map(|call| call.event)
+            .collect::<Vec<_>>();
+        assert_eq!(updates[..], ["input", "input"]);
+    }
+
+    #[test]
+    fn passive_policy() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let passive = |policy| {
+            let log = CallLog::default();
+            let overlay = RecordingLayer::new("overlay", &log, summary);
+            let mut manager =
+                LayerManager::from_layer(Box::new(Scripted(Some(Change::add_one(overlay)))));
+            manager.set_passive_policy(policy);
+            let mut state = GlobalState;
+
+            manager.update(&mut state, Event::Input);
+            manager.update(&mut state, Event::Idle);
+            let events = calls(&log, None, CallKind::Passive)
+                .iter()
+                .map(|call| call.event)
+                .collect::<Vec<_>>();
+            events
+        };
+
+        assert_eq!(passive(PassivePolicy::All)[..], ["input", "idle"]);
+        assert_eq!(passive(PassivePolicy::SkipNewlyAdded)[..], ["idle"]);
+    }
+
+    #[test]
+    fn passive_order() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let passive = |order| {
+            let log = CallLog::default();
+            let upper = RecordingLayer::new("upper", &log, summary);
+            let mut manager = LayerManager::new(vec![
+                Box::new(RecordingLayer::new("lower", &log, summary)),
+                Box::new(Scripted(Some(Change::add_one(upper)))),
+            ]);
+            manager.set_passive_order(order);
+            manager.update(&mut GlobalState, Event::Idle);
+            let layers = calls(&log, None, CallKind::Passive)
+                .iter()
+                .map(|call| call.layer)
+                .collect::<Vec<_>>();
+            layers
+        };
+
+        assert_eq!(passive(PassiveOrder::BottomUp)[..], ["lower", "upper"]);
+        assert_eq!(passive(PassiveOrder::TopDown)[..], ["upper", "lower"]);
+    }
+
+    #[test]
+    fn dispatch_order() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let updated = |order| {
+            let log = CallLog::default();
+            let mut manager = LayerManager::new(vec![
+                Box::new(
+                    RecordingLayer::new("decode", &log, summary)
+                        .with_script([Change::pass(), Change::none()]),
+                ),
+                Box::new(
+                    RecordingLayer::new("validate", &log, summary).with_script([Change::pass()]),
+                ),
+                Box::new(RecordingLayer::new("apply", &log, summary)),
+            ]);
+            manager.set_dispatch_order(order);
+            assert_eq!(manager.dispatch_order(), order);
+            manager.update(&mut GlobalState, Event::Input);
+            manager.update(&mut GlobalState, Event::Idle);
+            let layers = calls(&log, None, CallKind::Update)
+                .iter()
+                .map(|call| (call.layer, call.event))
+                .collect::<Vec<_>>();
+            layers
+        };
+
+        assert_eq!(
+            updated(DispatchOrder::TopDown)[..],
+            [("apply", "input"), ("apply", "idle")]
+        );
+        assert_eq!(
+            updated(DispatchOrder::BottomUp)[..],
+            [
+                ("decode", "input"),
+                ("validate", "input"),
+                ("apply", "input"),
+                ("decode", "idle"),
+            ]
+        );
+
+        let mut manager = LayerManager::new(vec![
+            Box::new(Scripted(Some(Change::add_one(Tagged(9))))),
+            Box::new(Tagged(1)),
+        ]);
+        manager.set_dispatch_order(DispatchOrder::BottomUp);
+        manager.update(&mut GlobalState, Event::Idle);
+        let tags = manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>();
+        assert_eq!(tags[..], [0, 9, 1]);
+    }
+
+    #[test]
+    fn sleep() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let lower = RecordingLayer::new("lower", &log, summary)
+            .with_script(vec![Change::none(), Change::wake()]);
+        let upper = RecordingLayer::new("upper", &log, summary).with_script(vec![Change::sleep()]);
+        let mut manager = LayerManager::new(vec![Box::new(lower), Box::new(upper)]);
+        let mut state = GlobalState;
+        let count = |kind| {
+            let count = |layer| calls(&log, Some(layer), kind).len();
+            (count("upper"), count("lower"))
+        };
+
+        manager.update(&mut state, Event::Idle);
+        assert!(manager.is_asleep(1));
+        assert!(!manager.is_asleep(0));
+        assert_eq!(count(CallKind::Update), (1, 0));
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(count(CallKind::Update), (1, 1));
+        assert_eq!(count(CallKind::Passive), (2, 2));
+
+        manager.update(&mut state, Event::Exit);
+        assert!(!manager.is_asleep(1));
+        assert_eq!(count(CallKind::Update), (1, 2));
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(count(CallKind::Update), (2, 2));
+
+        manager.set_asleep(1, true);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(count(CallKind::Update), (2, 3));
+        manager.set_asleep(1, false);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(count(CallKind::Update), (3, 3));
+        assert_eq!(count(CallKind::Passive), (6, 6));
+
+        let lower_events = calls(&log, Some("lower"), CallKind::Update)
+            .iter()
+            .map(|call| call.event)
+            .collect::<Vec<_>>();
+        assert_eq!(lower_events[..], ["input", "exit", "input"]);
+    }
+
+    pub struct Logged {
+        name: &'static str,
+        priority: i32,
+        log: alloc::rc::Rc<core::cell::RefCell<Vec<&'static str>>>,
+    }
+
+    impl Layer<GlobalState, Event> for Logged {
+        fn passive_update(&mut self, _state: &mut GlobalState, _event: &Event) {
+            self.log.borrow_mut().push(self.name);
+        }
+
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            self.log.borrow_mut().push(self.name);
+            match event {
+                Event::Input => Change::add_one(Logged {
+                    name: "added",
+                    priority: 0,
+                    log: self.log.clone(),
+                }),
+                Event::Idle => Change::pass(),
+                Event::Exit => Change::remove(),
+            }
+        }
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+    }
+
+    #[test]
+    fn priority() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let layer = |name, priority| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Logged {
+                name,
+                priority,
+                log: log.clone(),
+            })
+        };
+        let mut manager = LayerManager::new(vec![
+            layer("console", 1),
+            layer("world", 0),
+            layer("debug", 2),
+            layer("tooltip", 0),
+        ]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(log.borrow()[..4], ["debug", "console", "tooltip", "world"]);
+        assert_eq!(log.borrow()[4..], ["console", "world", "debug", "tooltip"]);
+        log.borrow_mut().clear();
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(
+            log.borrow()[..],
+            ["debug", "console", "world", "debug", "added", "tooltip"]
+        );
+        log.borrow_mut().clear();
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 4);
+        log.borrow_mut().clear();
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(
+            log.borrow()[..],
+            ["console", "tooltip", "added", "world", "console", "world", "added", "tooltip"]
+        );
+    }
+
+    #[test]
+    fn fold() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let layer = |name, priority| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Logged {
+                name,
+                priority,
+                log: log.clone(),
+            })
+        };
+        let manager = LayerManager::new(vec![
+            layer("world", 0),
+            layer("console", 1),
+            layer("debug", 2),
+            Box::new(MainLayer),
+        ]);
+
+        let prioritized = manager.fold(0, |count, layer| {
+            if layer.priority() > 0 {
+                count + 1
+            } else {
+                count
+            }
+        });
+        assert_eq!(prioritized, 2);
+
+        let mut total = 0;
+        manager.for_each(|layer| total += layer.priority());
+        assert_eq!(total, 3);
+    }
+
+    // A layer, whose priority rises with every event it handles.
+    #[derive(Clone)]
+    pub struct Steps(u32);
+
+    impl Layer<GlobalState, Event> for Steps {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            self.0 += 1;
+            match event {
+                Event::Input => Change::add_one(Steps(0)),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+
+        fn priority(&self) -> i32 {
+            self.0 as i32
+        }
+
+        fn try_clone(&self) -> Option<Box<dyn Layer<GlobalState, Event>>> {
+            Some(self.clone_box())
+        }
+    }
+
+    #[test]
+    fn clone_stack() {
+        let mut manager = LayerManager::new(vec![Box::new(Steps(0))]);
+        let mut state = GlobalState;
+        manager.update(&mut state, Event::Input);
+
+        let mut copy = manager.try_clone_stack().unwrap();
+        manager.update(&mut state, Event::Input);
+        copy.update(&mut state, Event::Idle);
+        copy.update(&mut state, Event::Exit);
+
+        let priorities = |manager: &LayerManager<GlobalState, Event>| {
+            let mut priorities = Vec::new();
+            manager.for_each(|layer| priorities.push(layer.priority()));
+            priorities
+        };
+        assert_eq!(priorities(&manager), [2, 0, 0]);
+        assert_eq!(priorities(&copy), [0]);
+
+        let mut mixed = LayerManager::new(vec![Box::new(Steps(0)), Box::new(MainLayer)]);
+        mixed.update(&mut state, Event::Idle);
+        let error = mixed.try_clone_stack().err().unwrap();
+        assert_eq!(error.index, 1);
+        assert_eq!(error.name, core::any::type_name::<MainLayer>());
+    }
+
+    #[test]
+    fn snapshot() {
+        let priorities = |manager: &LayerManager<GlobalState, Event>| {
+            manager
+                .iter()
+                .map(|layer| layer.priority())
+                .collect::<Vec<_>>()
+        };
+        let mut manager = LayerManager::new(vec![Box::new(Steps(0))]);
+        let mut state = GlobalState;
+        manager.update(&mut state, Event::Input);
+        manager.set_asleep(1, true);
+
+        let snapshot = manager.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 2);
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Exit);
+        manager.set_asleep(0, false);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(priorities(&manager)[..], [0]);
+
+        manager.restore(snapshot);
+        assert_eq!(priorities(&manager)[..], [1, 0]);
+        assert!(manager.is_asleep(1));
+        manager.update(&mut state, Event::Input);
+        assert_eq!(priorities(&manager)[..], [2, 0, 0]);
+
+        manager.push(Box::new(MainLayer));
+        let error = manager.snapshot().err().unwrap();
+        assert_eq!(error.index, 3);
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Broken(&'static str);
+
+    pub struct Faulty(&'static str);
+
+    impl Layer<GlobalState, Event> for Faulty {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::pass()
+        }
+
+        fn try_update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Result<Change<GlobalState, Event>, LayerError> {
+            match event {
+                Event::Input => Err(LayerError::new(Broken(self.0))),
+                _ => Ok(Change::pass()),
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn error_policies() {
+        let mut state = GlobalState;
+        let stack = || -> LayerManager<GlobalState, Event> {
+            LayerManager::new(vec![
+                Box::new(MainLayer),
+                Box::new(Faulty("lower")),
+                Box::new(Faulty("upper")),
+            ])
+        };
+
+        let mut manager = stack();
+        assert_eq!(manager.error_policy(), ErrorPolicy::Abort);
+        let failure = manager.try_update(&mut state, Event::Input).unwrap_err();
+        assert_eq!((failure.index, failure.name), (2, "upper"));
+        assert_eq!(failure.error.downcast_ref(), Some(&Broken("upper")));
+        assert_eq!(manager.len(), 3);
+        assert!(manager.try_update(&mut state, Event::Idle).is_ok());
+
+        let mut manager = stack();
+        manager.set_error_policy(ErrorPolicy::Skip);
+        let failure = manager.try_update(&mut state, Event::Input).unwrap_err();
+        assert_eq!(failure.policy, ErrorPolicy::Skip);
+        assert_eq!(
+            failure.error.downcast::<Broken>().ok(),
+            Some(Broken("upper"))
+        );
+        assert_eq!(manager.len(), 4);
+
+        let mut manager = stack();
+        manager.set_error_policy(ErrorPolicy::Remove);
+        let failure = manager.try_update(&mut state, Event::Input).unwrap_err();
+        assert_eq!(failure.index, 2);
+        assert!(!failure.error.is::<()>());
+        assert_eq!(manager.len(), 2);
+        let mut names = Vec::new();
+        manager.for_each(|layer| names.push(layer.name()));
+        assert_eq!(
+            names,
+            [
+                core::any::type_name::<MainLayer>(),
+                core::any::type_name::<TopLayer>()
+            ]
+        );
+    }
+
+    pub struct Wrapper<L>(L);
+
+    impl<L: Layer<GlobalState, Event>> Layer<GlobalState, Event> for Wrapper<L> {
+        fn update(&mut self, state: &mut GlobalState, event: &Event) -> Change<GlobalState, Event> {
+            self.0.update(state, event)
+        }
+    }
+
+    #[test]
+    fn forwarding() {
+        let boxed: Box<dyn Layer<GlobalState, Event>> = Box::new(MainLayer);
+        let mut manager = LayerManager::new(vec![Box::new(Wrapper(boxed))]);
+        let mut state = GlobalState;
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 2);
+
+        let mut top = TopLayer;
+        let mut borrowed = Wrapper(&mut top);
+        assert_eq!(
+            borrowed.update(&mut state, &Event::Exit).action,
+            ChangeAction::Remove
+        );
+
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(Some(TopLayer)),
+            Box::new(None::<TopLayer>),
+        ]);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 2);
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 1);
+        assert_eq!(
+            manager.iter().next().unwrap().name(),
+            core::any::type_name::<Option<TopLayer>>()
+        );
+    }
+
+    pub struct Attached {
+        index: usize,
+        log: alloc::rc::Rc<core::cell::RefCell<Vec<(usize, bool)>>>,
+    }
+
+    impl Layer<GlobalState, Event> for Attached {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+
+        fn on_attach(&mut self) {
+            self.log.borrow_mut().push((self.index, true));
+        }
+
+        fn on_detach(&mut self) {
+            self.log.borrow_mut().push((self.index, false));
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let attached = |index| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Attached {
+                index,
+                log: log.clone(),
+            })
+        };
+        let mut layers: Vec<_> = (0..3).map(attached).collect();
+        layers.push(Box::new(Scripted(Some(Change::reset(attached(9))))));
+        let mut manager = LayerManager::new(layers);
+        log.borrow_mut().clear();
+
+        manager.update(&mut GlobalState, Event::Idle);
+        assert_eq!(manager.len(), 1);
+        assert_eq!(
+            log.borrow()[..],
+            [(2, false), (1, false), (0, false), (9, true)]
+        );
+
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(Scripted(Some(Change::clear([attached(4), attached(5)])))),
+        ]);
+        manager.update(&mut GlobalState, Event::Idle);
+        assert_eq!(manager.len(), 2);
+    }
+
+    pub struct Remapper {
+        captured: alloc::rc::Rc<core::cell::Cell<u32>>,
+    }
+
+    impl Layer<GlobalState, Event> for Remapper {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            match event {
+                Event::Input => Change::replace_and_dispatch([Box::new(Capture {
+                    captured: self.captured.clone(),
+                })
+                    as Box<dyn Layer<GlobalState, Event>>]),
+                _ => Change::pass(),
+            }
+        }
+    }
+
+    pub struct Capture {
+        captured: alloc::rc::Rc<core::cell::Cell<u32>>,
+    }
+
+    impl Layer<GlobalState, Event> for Capture {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            self.captured.set(self.captured.get() + 1);
+            Change::none()
+        }
+    }
+
+    pub struct Spawner;
+
+    impl Layer<GlobalState, Event> for Spawner {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::add_and_dispatch([Box::new(Spawner) as Box<dyn Layer<GlobalState, Event>>])
+        }
+    }
+
+    #[test]
+    fn add_and_dispatch() {
+        let captured = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(Remapper {
+                captured: captured.clone(),
+            }),
+        ]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(captured.get(), 1);
+        assert_eq!(manager.len(), 2);
+        assert!(manager.iter().last().unwrap().name().ends_with("Capture"));
+
+        let mut manager = LayerManager::from_layer(Box::new(Spawner));
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(
+            manager.len(),
+            LayerManager::<GlobalState, Event>::MAX_REDISPATCH + 2
+        );
+    }
+
+    pub struct Covered {
+        name: &'static str,
+        log: alloc::rc::Rc<core::cell::RefCell<Vec<(&'static str, bool)>>>,
+    }
+
+    impl Layer<GlobalState, Event> for Covered {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+
+        fn on_cover(&mut self) {
+            self.log.borrow_mut().push((self.name, true));
+        }
+
+        fn on_uncover(&mut self) {
+            self.log.borrow_mut().push((self.name, false));
+        }
+    }
+
+    #[test]
+    fn dirty() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer)]);
+        let mut state = GlobalState;
+        assert!(!manager.take_dirty());
+
+        manager.update(&mut state, Event::Idle);
+        assert!(!manager.needs_redraw());
+
+        manager.update(&mut state, Event::Input);
+        assert!(manager.needs_redraw());
+        assert!(manager.take_dirty());
+        assert!(!manager.needs_redraw());
+
+        manager.update(&mut state, Event::Exit);
+        assert!(manager.take_dirty());
+
+        manager.push(Box::new(Scripted(Some(Change::none().mark_dirty()))));
+        assert!(manager.take_dirty());
+        manager.update(&mut state, Event::Idle);
+        assert!(manager.take_dirty());
+        manager.update(&mut state, Event::Idle);
+        assert!(!manager.take_dirty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn catch_panics() {
+        struct Panicking;
+
+        impl Layer<GlobalState, Event> for Panicking {
+            fn passive_update(&mut self, _state: &mut GlobalState, event: &Event) {
+                if let Event::Exit = event {
+                    panic!("passive");
+                }
+            }
+
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                event: &Event,
+            ) -> Change<GlobalState, Event> {
+                match event {
+                    Event::Input => panic!("update"),
+                    _ => Change::pass(),
+                }
+            }
+        }
+
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(Panicking),
+            Box::new(Panicking),
+        ]);
+        let mut state = GlobalState;
+        manager.set_catch_panics(true);
+
+        manager.enable_history(4);
+
+        manager.update(&mut state, Event::Input);
+        let payload = manager.take_last_panic().unwrap();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"update"));
+        assert_eq!(manager.len(), 2);
+        assert_eq!(manager.find_type::<TopLayer>(), Some(1));
+        assert!(manager.undo());
+        assert!(!manager.undo());
+        assert_eq!(manager.len(), 1);
+        assert!(manager.redo());
+
+        manager.push(Box::new(Panicking));
+        manager.update(&mut state, Event::Idle);
+        assert!(manager.take_last_panic().is_none());
+        let removed = manager.update_taking_removed(&mut state, Event::Exit);
+        assert!(manager.take_last_panic().is_some());
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].layer_type_id(), TypeId::of::<TopLayer>());
+        assert_eq!(manager.len(), 1);
+        assert_eq!(manager.find_type::<MainLayer>(), Some(0));
+    }
+
+    #[test]
+    fn active_layer_count() {
+        struct Pane {
+            enabled: bool,
+            opaque: bool,
+        }
+
+        impl Layer<GlobalState, Event> for Pane {
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                _event: &Event,
+            ) -> Change<GlobalState, Event> {
+                Change::none()
+            }
+
+            fn enabled(&self) -> bool {
+                self.enabled
+            }
+
+            fn is_opaque(&self) -> bool {
+                self.opaque
+            }
+        }
+
+        let pane = |enabled, opaque| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Pane { enabled, opaque })
+        };
+        let mut manager = LayerManager::new(vec![
+            pane(true, false),
+            pane(true, true),
+            pane(true, false),
+            pane(false, false),
+            pane(false, true),
+            pane(true, false),
+        ]);
+        assert_eq!(manager.active_layer_count(), 3);
+
+        manager.splice(1..2, vec![pane(false, true)]);
+        assert_eq!(manager.active_layer_count(), 3);
+        manager.truncate(0);
+        assert_eq!(manager.active_layer_count(), 0);
+    }
+
+    #[test]
+    fn input_occlusion() {
+        use testing::{CallKind, CallLog, RecordingLayer};
+
+        struct Modal;
+
+        impl Layer<GlobalState, Event> for Modal {
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                _event: &Event,
+            ) -> Change<GlobalState, Event> {
+                Change::pass()
+            }
+
+            fn is_opaque(&self) -> bool {
+                true
+            }
+        }
+
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(RecordingLayer::new("background", &log, summary)),
+            Box::new(Modal),
+        ]);
+        let mut state = GlobalState;
+
+        manager.set_input_occlusion(true);
+        assert!(manager.input_occlusion());
+        assert_eq!(
+            manager.update(&mut state, Event::Input),
+            Dispatch::PassedThrough
+        );
+        assert!(log
+            .borrow()
+            .iter()
+            .all(|call| call.kind != CallKind::Update));
+
+        manager.set_input_occlusion(false);
+        assert_eq!(manager.update(&mut state, Event::Input), Dispatch::Consumed);
+        assert!(log
+            .borrow()
+            .iter()
+            .any(|call| call.kind == CallKind::Update));
+    }
+
+    #[test]
+    fn pop_until() {
+        let mut manager = LayerManager::new(
+            [1, 2, 3, 4]
+                .iter()
+                .map(|&tags| -> Box<dyn Layer<GlobalState, Event>> { Box::new(Tagged(tags)) })
+                .collect(),
+        );
+
+        assert_eq!(manager.pop_until(|layer| layer.tags() == 4), 0);
+        assert_eq!(manager.pop_until(|layer| layer.tags() == 1), 3);
+        assert_eq!(manager.len(), 1);
+        assert_eq!(manager.pop_until(|_| false), 1);
+        assert!(manager.is_empty());
+        assert_eq!(manager.pop_until(|_| false), 0);
+    }
+
+    #[test]
+    fn splice() {
+        let tagged = |tags: &[u32]| {
+            tags.iter()
+                .map(|&tags| -> Box<dyn Layer<GlobalState, Event>> { Box::new(Tagged(tags)) })
+                .collect::<Vec<_>>()
+        };
+        let tags = |layers: &mut dyn Iterator<Item = &dyn Layer<GlobalState, Event>>| {
+            layers.map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+        let mut manager = LayerManager::new(tagged(&[1, 2, 3, 4]));
+
+        let removed = manager.splice(1..3, tagged(&[5, 6, 7]));
+        assert_eq!(tags(&mut removed.iter().map(|layer| &**layer))[..], [2, 3]);
+        assert_eq!(tags(&mut manager.iter())[..], [1, 5, 6, 7, 4]);
+
+        let removed = manager.splice(0..2, Vec::new());
+        assert_eq!(tags(&mut removed.iter().map(|layer| &**layer))[..], [1, 5]);
+        assert_eq!(tags(&mut manager.iter())[..], [6, 7, 4]);
+
+        let removed = manager.splice(3..3, tagged(&[8]));
+        assert!(removed.is_empty());
+        assert_eq!(tags(&mut manager.iter())[..], [6, 7, 4, 8]);
+
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let covered = |name| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Covered {
+                name,
+                log: log.clone(),
+            })
+        };
+        let mut manager = LayerManager::new(vec![covered("a"), covered("b")]);
+        log.borrow_mut().clear();
+        manager.splice(1..2, vec![covered("c"), covered("d")]);
+        assert_eq!(log.borrow()[..], [("c", true)]);
+    }
+
+    fn history_tags(manager: &LayerManager<GlobalState, Event>) -> Vec<u32> {
+        manager.iter().map(|layer| layer.tags()).collect()
+    }
+
+    #[test]
+    fn undo() {
+        use testing::{CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager =
+            LayerManager::new(vec![
+                Box::new(RecordingLayer::new("root", &log, |_: &Event| ())) as Box<dyn Layer<_, _>>,
+            ]);
+        let mut state = GlobalState;
+        manager.enable_history(8);
+        assert!(!manager.undo());
+
+        manager.push(Box::new(Scripted(Some(Change::add_one(Tagged(2))))));
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(history_tags(&manager)[..], [0, 0, 2]);
+        manager.push(Box::new(Scripted(Some(Change::close()))));
+        manager.update(&mut state, Event::Idle);
+        assert!(manager.is_empty());
+
+        assert!(manager.undo());
+        assert_eq!(history_tags(&manager)[..], [0, 0, 2, 0]);
+        assert!(manager.undo());
+        assert!(manager.undo());
+        assert_eq!(history_tags(&manager)[..], [0, 0]);
+        assert!(manager.redo());
+        assert_eq!(history_tags(&manager)[..], [0, 0, 2]);
+        assert!(manager.undo());
+        assert!(manager.undo());
+        assert!(!manager.undo());
+        assert_eq!(manager.len(), 1);
+
+        log.borrow_mut().clear();
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(log.borrow()[0].kind, CallKind::Update);
+
+        assert!(manager.redo());
+        manager.push(Box::new(Tagged(5)));
+        assert!(!manager.redo());
+        assert_eq!(history_tags(&manager)[..], [0, 0, 5]);
+        assert!(manager.undo());
+        assert_eq!(history_tags(&manager)[..], [0, 0]);
+    }
+
+    #[test]
+    fn history_limit() {
+        let mut manager = LayerManager::<GlobalState, Event>::default();
+        manager.enable_history(2);
+        for tags in 1..=4 {
+            manager.push(Box::new(Tagged(tags)));
+        }
+        manager.truncate(1);
+        assert!(manager.undo());
+        assert!(manager.undo());
+        assert!(!manager.undo());
+        assert_eq!(history_tags(&manager)[..], [1, 2, 3]);
+
+        manager.pop(&mut GlobalState);
+        assert!(!manager.undo());
+        manager.disable_history();
+        manager.push(Box::new(Tagged(6)));
+        assert!(!manager.undo());
+    }
+
+    #[test]
+    fn transaction() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let covered = |name| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Covered {
+                name,
+                log: log.clone(),
+            })
+        };
+        let take = || core::mem::take(&mut *log.borrow_mut());
+        let mut manager = LayerManager::new(vec![covered("a"), covered("b")]);
+        let mut state = GlobalState;
+        assert_eq!(take()[..], [("a", true)]);
+
+        manager.push(covered("c"));
+        assert_eq!(take()[..], [("b", true)]);
+        manager.pop(&mut state);
+        assert_eq!(take()[..], [("b", false)]);
+
+        manager.transaction(|editor| {
+            editor.pop();
+            editor.pop();
+            editor.push(covered("d"));
+            editor.push(covered("e"));
+            editor.push(covered("f"));
+            editor.swap(1, 2);
+            editor.pop();
+        });
+        assert_eq!(manager.len(), 2);
+        assert_eq!(take()[..], [("d", true)]);
+
+        manager.transaction(|editor| editor.truncate(1));
+        assert_eq!(take()[..], [("d", false)]);
+    }
+
+    #[test]
+    fn truncate() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let mut manager = LayerManager::new(
+            (0..4)
+                .map(|index| -> Box<dyn Layer<GlobalState, Event>> {
+                    Box::new(Attached {
+                        index,
+                        log: log.clone(),
+                    })
+                })
+                .collect(),
+        );
+        assert_eq!(
+            log.borrow()[..],
+            [(0, true), (1, true), (2, true), (3, true)]
+        );
+        log.borrow_mut().clear();
+
+        manager.truncate(4);
+        manager.truncate(5);
+        assert_eq!(manager.len(), 4);
+        assert!(log.borrow().is_empty());
+
+        manager.truncate(2);
+        assert_eq!(manager.len(), 2);
+        assert_eq!(log.borrow()[..], [(3, false), (2, false)]);
+        log.borrow_mut().clear();
+
+        manager.truncate(0);
+        assert!(manager.is_empty());
+        assert_eq!(log.borrow()[..], [(1, false), (0, false)]);
+    }
+
+    #[test]
+    fn observer() {
+        let observed = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let log = observed.clone();
+        manager.set_observer(Box::new(move |index, action| {
+            log.borrow_mut().push((index, *action))
+        }));
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 2);
+
+        use ChangeAction::*;
+        assert_eq!(
+            observed.borrow()[..],
+            [(1, None), (1, Pass), (0, None), (2, Remove)]
+        );
+    }
+
+    pub struct Background;
+
+    impl Layer<GlobalState, Event> for Background {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+    }
+
+    pub struct Requester;
+
+    impl Layer<GlobalState, Event> for Requester {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            match event {
+                Event::Input => {
+                    Change::add_bottom([Box::new(Background) as Box<dyn Layer<GlobalState, Event>>])
+                }
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+    }
+
+    #[test]
+    fn add_bottom() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(Requester)]);
+        let mut state = GlobalState;
+        let names = |manager: &LayerManager<GlobalState, Event>| {
+            manager.iter().map(|layer| layer.name()).collect::<Vec<_>>()
+        };
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(
+            names(&manager)[..],
+            [
+                core::any::type_name::<Background>(),
+                core::any::type_name::<MainLayer>(),
+                core::any::type_name::<Requester>(),
+            ]
+        );
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(
+            names(&manager)[..],
+            [
+                core::any::type_name::<Background>(),
+                core::any::type_name::<MainLayer>(),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let inner = LayerManager::new(vec![Box::new(LayerManager::new(vec![Box::new(TopLayer)]))
+            as Box<dyn Layer<GlobalState, Event>>]);
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(inner)]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(manager.len(), 2);
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(manager.len(), 3);
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 2);
+        assert_eq!(
+            manager.iter().map(|layer| layer.name()).collect::<Vec<_>>()[..],
+            [
+                core::any::type_name::<MainLayer>(),
+                core::any::type_name::<TopLayer>(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_to_root() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let attached = |index| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Attached {
+                index,
+                log: log.clone(),
+            })
+        };
+        let mut manager = LayerManager::default();
+        assert_eq!(manager.reset_to_root(vec![attached(0)]), Err(Inactive));
+        assert!(manager.is_empty());
+        log.borrow_mut().clear();
+
+        manager = LayerManager::new((0..3).map(attached).collect());
+        log.borrow_mut().clear();
+        assert_eq!(
+            manager.reset_to_root(vec![attached(3), attached(4)]),
+            Ok(())
+        );
+        assert_eq!(manager.len(), 3);
+        assert_eq!(
+            log.borrow()[..],
+            [(2, false), (1, false), (3, true), (4, true)]
+        );
+        log.borrow_mut().clear();
+
+        manager.truncate(0);
+        assert_eq!(log.borrow()[..], [(4, false), (3, false), (0, false)]);
+    }
+
+    pub struct Form {
+        value: u32,
+    }
+
+    impl Layer<GlobalState, Event> for Form {
+        fn update(&mut self, state: &mut GlobalState, event: &Event) -> Change<GlobalState, Event> {
+            self.update_output(state, event).0
+        }
+
+        fn update_output(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> (Change<GlobalState, Event>, Option<Box<dyn Any>>) {
+            match event {
+                Event::Input => (Change::pass(), Some(Box::new(self.value))),
+                Event::Idle => (Change::pass(), None),
+                Event::Exit => (Change::remove(), Some(Box::new("closed"))),
+            }
+        }
+    }
+
+    pub struct Caption;
+
+    impl Layer<GlobalState, Event> for Caption {
+        fn update(&mut self, state: &mut GlobalState, event: &Event) -> Change<GlobalState, Event> {
+            self.update_output(state, event).0
+        }
+
+        fn update_output(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> (Change<GlobalState, Event>, Option<Box<dyn Any>>) {
+            (Change::pass(), Some(Box::new("caption")))
+        }
+    }
+
+    #[test]
+    fn update_collect() {
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(Form { value: 1 }),
+            Box::new(Form { value: 2 }),
+        ]);
+        let mut state = GlobalState;
+
+        assert_eq!(manager.update_collect::<u32>(&mut state, Event::Idle), []);
+        assert_eq!(
+            manager.update_collect::<u32>(&mut state, Event::Input),
+            [2, 1]
+        );
+        assert_eq!(manager.update_collect::<u32>(&mut state, Event::Exit), []);
+        assert_eq!(
+            manager.update_collect::<&str>(&mut state, Event::Exit),
+            ["closed"]
+        );
+        assert_eq!(manager.len(), 2);
+
+        let clicks = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let draws = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        manager.push(Box::new(Toolbar {
+            enabled: true,
+            clicks: clicks.clone(),
+            draws: draws.clone(),
+        }));
+        manager.pause(true);
+        assert_eq!(manager.update_collect::<u32>(&mut state, Event::Input), []);
+        assert_eq!((clicks.get(), draws.get()), (0, 1));
+    }
+
+    #[test]
+    fn update_collect_mixed() {
+        let mut manager = LayerManager::new(vec![
+            Box::new(Form { value: 1 }),
+            Box::new(Caption),
+            Box::new(Form { value: 2 }),
+        ]);
+        let mut state = GlobalState;
+
+        assert_eq!(
+            manager.update_collect::<u32>(&mut state, Event::Input),
+            [2, 1]
+        );
+        assert_eq!(
+            manager.update_collect::<&str>(&mut state, Event::Input),
+            ["caption"]
+        );
+        assert_eq!(manager.update_collect::<u64>(&mut state, Event::Input), []);
+    }
+
+    pub struct Scripted(Option<Change<GlobalState, Event>>);
+
+    impl Layer<GlobalState, Event> for Scripted {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            self.0.take().unwrap_or_else(Change::none)
+        }
+    }
+
+    #[test]
+    fn change_constructors() {
+        let depth = |change| {
+            let mut manager =
+                LayerManager::new(vec![Box::new(MainLayer), Box::new(Scripted(Some(change)))]);
+            manager.update(&mut GlobalState, Event::Idle);
+            manager.len()
+        };
+
+        assert_eq!(
+            depth(Change::add([
+                Box::new(TopLayer) as Box<dyn Layer<GlobalState, Event>>,
+                Box::new(TopLayer),
+            ])),
+            4
+        );
+        assert_eq!(
+            depth(Change::replace((0..3).map(
+                |_| -> Box<dyn Layer<GlobalState, Event>> { Box::new(TopLayer) }
+            ))),
+            4
+        );
+        assert_eq!(depth(Change::add_one(TopLayer)), 3);
+        assert_eq!(depth(Change::replace_one(TopLayer)), 2);
+        let stack: Vec<Box<dyn Layer<GlobalState, Event>>> = vec![Box::new(TopLayer)];
+        assert_eq!(depth(Change::add(stack)), 3);
+    }
+
+    #[test]
+    fn update_checked() {
+        let mut manager = LayerManager::new(vec![Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        assert_eq!(manager.update_checked(&mut state, Event::Idle), Ok(()));
+        assert_eq!(manager.update(&mut state, Event::Exit), Dispatch::Consumed);
+        assert_eq!(manager.update(&mut state, Event::Exit), Dispatch::Empty);
+        assert_eq!(
+            manager.update_checked(&mut state, Event::Idle),
+            Err(Inactive)
+        );
+    }
+
+    pub struct Tagged(u32);
+
+    impl Layer<GlobalState, Event> for Tagged {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+
+        fn tags(&self) -> u32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn tags() {
+        const DEBUG: u32 = 1;
+        const OVERLAY: u32 = 2;
+        const HUD: u32 = 4;
+        let mut manager = LayerManager::new(
+            [0, DEBUG, DEBUG | OVERLAY, OVERLAY, HUD | OVERLAY, DEBUG]
+                .iter()
+                .map(|&tags| -> Box<dyn Layer<GlobalState, Event>> { Box::new(Tagged(tags)) })
+                .collect(),
+        );
+
+        assert_eq!(manager.count_by_tag(DEBUG), 3);
+        assert_eq!(manager.count_by_tag(OVERLAY), 3);
+        assert_eq!(manager.count_by_tag(DEBUG | HUD), 4);
+        assert!(!manager.any_with_tag(8));
+
+        assert_eq!(manager.remove_by_tag(DEBUG), 3);
+        assert!(!manager.any_with_tag(DEBUG));
+        assert_eq!(
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()[..],
+            [0, OVERLAY, HUD | OVERLAY]
+        );
+        assert_eq!(manager.remove_by_tag(DEBUG), 0);
+    }
+
+    pub struct Window {
+        id: u32,
+        z_order: i32,
+    }
+
+    impl Layer<GlobalState, Event> for Window {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+
+        fn tags(&self) -> u32 {
+            self.id
+        }
+
+        fn z_order(&self) -> i32 {
+            self.z_order
+        }
+    }
+
+    #[test]
+    fn z_order() {
+        let mut manager = LayerManager::default();
+        for &(id, z_order) in &[(0, 5), (1, -1), (2, 5), (3, 10), (4, 0), (5, -1)] {
+            manager.insert_z_ordered(Box::new(Window { id, z_order }));
+        }
+        assert_eq!(
+            manager
+                .iter()
+                .map(|layer| (layer.tags(), layer.z_order()))
+                .collect::<Vec<_>>()[..],
+            [(1, -1), (5, -1), (4, 0), (0, 5), (2, 5), (3, 10)]
+        );
+        assert_eq!(
+            manager.insert_z_ordered(Box::new(Window { id: 6, z_order: 0 })),
+            3
+        );
+
+        manager.push(Box::new(Window { id: 7, z_order: -5 }));
+        manager.insert_at(0, Box::new(Window { id: 8, z_order: 20 }));
+        assert_eq!(manager.len(), 9);
+        assert_eq!(manager.iter().next().map(|layer| layer.tags()), Some(8));
+    }
+
+    #[test]
+    fn sort_by_z() {
+        let windows = |windows: &[(u32, i32)]| {
+            windows
+                .iter()
+                .map(|&(id, z_order)| -> Box<dyn Layer<GlobalState, Event>> {
+                    Box::new(Window { id, z_order })
+                })
+                .collect()
+        };
+        let arrangement = |manager: &LayerManager<GlobalState, Event>| {
+            manager
+                .iter()
+                .map(|layer| (layer.tags(), layer.z_order()))
+                .collect::<Vec<_>>()
+        };
+        let shuffled = [(0, 5), (1, -1), (2, 5), (3, 0), (4, 10), (5, -1)];
+        let mut manager = LayerManager::new(windows(&shuffled));
+        manager.enable_history(1);
+        manager.sort_by_z();
+        assert_eq!(
+            arrangement(&manager)[..],
+            [(1, -1), (5, -1), (3, 0), (0, 5), (2, 5), (4, 10)]
+        );
+        manager.sort_by_key(|layer| core::cmp::Reverse(layer.tags()));
+        assert_eq!(
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()[..],
+            [5, 4, 3, 2, 1, 0]
+        );
+        assert!(manager.undo());
+        assert_eq!(
+            arrangement(&manager)[..],
+            [(1, -1), (5, -1), (3, 0), (0, 5), (2, 5), (4, 10)]
+        );
+
+        struct Ordered(i32, Covered);
+
+        impl Layer<GlobalState, Event> for Ordered {
+            fn update(
+                &mut self,
+                state: &mut GlobalState,
+                event: &Event,
+            ) -> Change<GlobalState, Event> {
+                self.1.update(state, event)
+            }
+
+            fn on_cover(&mut self) {
+                self.1.on_cover();
+            }
+
+            fn on_uncover(&mut self) {
+                self.1.on_uncover();
+            }
+
+            fn z_order(&self) -> i32 {
+                self.0
+            }
+        }
+
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let ordered = |z_order, name| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Ordered(
+                z_order,
+                Covered {
+                    name,
+                    log: log.clone(),
+                },
+            ))
+        };
+        let mut manager =
+            LayerManager::new(vec![ordered(1, "b"), ordered(2, "c"), ordered(0, "a")]);
+        log.borrow_mut().clear();
+        manager.sort_by_z();
+        assert_eq!(log.borrow()[..], [("a", true), ("c", false)]);
+    }
+
+    #[test]
+    fn stack_observer() {
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+
+        type Calls = Rc<RefCell<Vec<(&'static str, usize, u32)>>>;
+
+        struct Recorder(Calls);
+
+        impl StackObserver<GlobalState, Event> for Recorder {
+            fn on_added(&mut self, index: usize, layer: &dyn Layer<GlobalState, Event>) {
+                self.0.borrow_mut().push(("added", index, layer.tags()));
+            }
+
+            fn on_removed(&mut self, index: usize, layer: &dyn Layer<GlobalState, Event>) {
+                self.0.borrow_mut().push(("removed", index, layer.tags()));
+            }
+
+            fn on_cleared(&mut self, count: usize) {
+                self.0.borrow_mut().push(("cleared", count, 0));
+            }
+        }
+
+        let calls = Calls::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(Tagged(1)),
+            Box::new(Scripted(Some(Change::replace([
+                Box::new(Tagged(2)) as Box<dyn Layer<GlobalState, Event>>,
+                Box::new(Tagged(3)),
+            ])))),
+        ]);
+        let mut state = GlobalState;
+        manager.set_stack_observer(Box::new(Recorder(calls.clone())));
+
+        manager.update(&mut state, Event::Idle);
+        manager.push(Box::new(Scripted(Some(Change::close()))));
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(
+            calls.borrow()[..],
+            [
+                ("added", 2, 2),
+                ("added", 3, 3),
+                ("removed", 1, 0),
+                ("added", 3, 0),
+                ("removed", 3, 0),
+                ("removed", 2, 3),
+                ("removed", 1, 2),
+                ("removed", 0, 1),
+                ("cleared", 4, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_taking_removed() {
+        let mut manager = LayerManager::new(vec![
+            Box::new(Tagged(1)),
+            Box::new(Tagged(2)),
+            Box::new(Scripted(Some(Change::remove()))),
+        ]);
+        let mut state = GlobalState;
+
+        let removed = manager.update_taking_removed(&mut state, Event::Idle);
+        assert_eq!(
+            removed.iter().map(|layer| layer.name()).collect::<Vec<_>>()[..],
+            [core::any::type_name::<Scripted>()]
+        );
+        assert!(manager
+            .update_taking_removed(&mut state, Event::Idle)
+            .is_empty());
+
+        manager.push(Box::new(Scripted(Some(Change::close()))));
+        let removed = manager.update_taking_removed(&mut state, Event::Idle);
+        assert!(manager.is_empty());
+        assert_eq!(
+            removed.iter().map(|layer| layer.tags()).collect::<Vec<_>>()[..],
+            [0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn paused() {
+        use testing::{CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(RecordingLayer::new("top", &log, summary)),
+        ]);
+        let mut state = GlobalState;
+
+        manager.set_paused(true);
+        assert!(manager.is_paused());
+        assert_eq!(manager.update(&mut state, Event::Input), Dispatch::Paused);
+        assert_eq!(manager.try_update(&mut state, Event::Input).ok(), Some(()));
+        assert!(manager
+            .update_collect::<()>(&mut state, Event::Input)
+            .is_empty());
+        assert!(log.borrow().is_empty());
+        assert_eq!(manager.len(), 2);
+
+        manager.set_paused(false);
+        assert_eq!(manager.update(&mut state, Event::Idle), Dispatch::Consumed);
+        assert_eq!(log.borrow().len(), 2);
+    }
+
+    #[test]
+    fn pause() {
+        use testing::{CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(RecordingLayer::new("top", &log, summary)),
+        ]);
+        let mut state = GlobalState;
+        let calls = || {
+            let calls = log
+                .borrow()
+                .iter()
+                .map(|call| call.kind)
+                .collect::<Vec<_>>();
+            log.borrow_mut().clear();
+            calls
+        };
+
+        manager.queue(Event::Input);
+        manager.pause(true);
+        manager.pause(true);
+        assert!(manager.is_paused());
+        assert!(manager.is_active());
+        assert_eq!(manager.update(&mut state, Event::Exit), Dispatch::Paused);
+        assert_eq!(manager.flush(&mut state), 0);
+        assert_eq!(calls()[..], [CallKind::Passive]);
+
+        manager.pause(false);
+        assert_eq!(manager.update(&mut state, Event::Exit), Dispatch::Paused);
+        assert!(calls().is_empty());
+        assert_eq!(manager.len(), 2);
+
+        manager.resume();
+        assert!(!manager.is_paused());
+        assert_eq!(manager.flush(&mut state), 1);
+        assert_eq!(calls()[..], [CallKind::Update, CallKind::Passive]);
+    }
+
+    #[test]
+    fn focus() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let mut manager = LayerManager::new(vec![
+            Box::new(
+                RecordingLayer::new("panel", &log, summary)
+                    .with_script([Change::none(), Change::pass()]),
+            ),
+            Box::new(RecordingLayer::new("overlay", &log, summary)),
+        ]);
+        let mut state = GlobalState;
+        let updated = || {
+            let updated = calls(&log, None, CallKind::Update)
+                .iter()
+                .map(|call| call.layer)
+                .collect::<Vec<_>>();
+            log.borrow_mut().clear();
+            updated
+        };
+
+        let panel = manager.id_at(0).unwrap();
+        manager.set_focus(panel);
+        assert_eq!(manager.focus(), Some(panel));
+        manager.update(&mut state, Event::Input);
+        assert_eq!(updated()[..], ["panel"]);
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(updated()[..], ["panel", "overlay"]);
+
+        manager.push(Box::new(TopLayer));
+        assert_eq!(manager.index_of(panel), Some(0));
+        assert!(manager.remove_at(0, &mut state).is_some());
+        assert_eq!(manager.index_of(panel), None);
+        assert_eq!(manager.focus(), None);
+        manager.set_asleep(1, true);
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(updated()[..], ["overlay"]);
+    }
+
+    #[test]
+    fn rfind() {
+        let manager = LayerManager::new(
+            [1, 2, 3, 2, 4]
+                .iter()
+                .map(|&tags| -> Box<dyn Layer<GlobalState, Event>> { Box::new(Tagged(tags)) })
+                .collect(),
+        );
+
+        assert_eq!(manager.rfind(|layer| layer.tags() == 3), Some(2));
+        assert_eq!(manager.rfind(|layer| layer.tags() == 2), Some(3));
+        assert_eq!(manager.rfind(|layer| layer.tags() & 1 != 0), Some(2));
+        assert_eq!(manager.rfind(|layer| layer.tags() == 5), None);
+    }
+
+    #[test]
+    fn find_type() {
+        use adapters::LayerExt;
+
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        manager.push(Box::new(Tagged(1)));
+        manager.push(Box::new(TopLayer.filter(|_: &GlobalState, _: &Event| true)));
+        manager.push(Box::new(Tagged(2)));
+
+        assert!(manager.contains_type::<MainLayer>());
+        assert!(!manager.contains_type::<Shell>());
+        assert_eq!(manager.find_type::<Tagged>(), Some(4));
+        assert_eq!(manager.find_type::<TopLayer>(), Some(3));
+        assert_eq!(manager.find_type::<Shell>(), None);
+    }
+
+    #[test]
+    fn count() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(Tagged(1))]);
+        manager.push(Box::new(Tagged(3)));
+        manager.push(Box::new(TopLayer));
+
+        assert_eq!(manager.count(|layer| layer.tags() & 2 != 0), 1);
+        assert_eq!(manager.count(|layer| layer.tags() != 0), 2);
+        assert_eq!(manager.count(|layer| layer.name().ends_with("TopLayer")), 1);
+        assert_eq!(manager.count(|_| false), 0);
+    }
+
+    pub struct Static {
+        dirty: alloc::rc::Rc<core::cell::Cell<bool>>,
+        draws: alloc::rc::Rc<core::cell::Cell<u32>>,
+    }
+
+    impl Layer<GlobalState, Event> for Static {
+        fn passive_update(&mut self, _state: &mut GlobalState, _event: &Event) {
+            self.draws.set(self.draws.get() + 1);
+            self.dirty.set(false);
+        }
+
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            if let Event::Input = event {
+                self.dirty.set(true);
+            }
+            Change::pass()
+        }
+
+        fn needs_passive(&self) -> bool {
+            self.dirty.get()
+        }
+    }
+
+    #[test]
+    fn needs_passive() {
+        let dirty = alloc::rc::Rc::new(core::cell::Cell::new(true));
+        let draws = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let mut manager = LayerManager::new(vec![Box::new(Static {
+            dirty: dirty.clone(),
+            draws: draws.clone(),
+        })]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(draws.get(), 1);
+        assert!(!dirty.get());
+
+        manager.update(&mut state, Event::Input);
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(draws.get(), 2);
+    }
+
+    #[test]
+    fn replace_top() {
+        let log = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let attached = |index| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Attached {
+                index,
+                log: log.clone(),
+            })
+        };
+        let mut manager = LayerManager::default();
+
+        assert!(manager.replace_top(attached(0)).is_none());
+        assert_eq!(manager.len(), 1);
+        manager.push(attached(1));
+        log.borrow_mut().clear();
+
+        let old = manager.replace_top(attached(2));
+        assert!(old.is_some());
+        assert_eq!(manager.len(), 2);
+        assert_eq!(log.borrow()[..], [(1, false), (2, true)]);
+    }
+
+    pub struct Unsaved {
+        changes: u32,
+    }
+
+    impl Layer<GlobalState, Event> for Unsaved {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::none()
+        }
+
+        fn on_remove_request(&mut self, _state: &mut GlobalState) -> bool {
+            self.changes = self.changes.saturating_sub(1);
+            self.changes == 0
+        }
+    }
+
+    #[test]
+    fn remove_request() {
+        let mut manager =
+            LayerManager::new(vec![Box::new(MainLayer), Box::new(Unsaved { changes: 2 })]);
+        let mut state = GlobalState;
+
+        assert!(manager.pop(&mut state).is_none());
+        assert_eq!(manager.len(), 2);
+        assert!(manager.pop(&mut state).is_some());
+        assert!(manager.remove_at(1, &mut state).is_none());
+        assert!(manager.remove_at(0, &mut state).is_some());
+        assert!(manager.pop(&mut state).is_none());
+    }
+
+    #[test]
+    fn dispatch_result() {
+        let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        let mut state = GlobalState;
+
+        assert_eq!(manager.update(&mut state, Event::Input), Dispatch::Consumed);
+        manager.truncate(1);
+        manager.push(Box::new(Scripted(Some(Change::pass()))));
+        manager.set_asleep(0, true);
+        assert_eq!(
+            manager.update(&mut state, Event::Idle),
+            Dispatch::PassedThrough
+        );
+        manager.truncate(0);
+        assert_eq!(manager.update(&mut state, Event::Idle), Dispatch::Empty);
+    }
+
+    #[test]
+    fn add_order() {
+        let added =
+            || (1..4).map(|tags| -> Box<dyn Layer<GlobalState, Event>> { Box::new(Tagged(tags)) });
+        let tags = |manager: &LayerManager<GlobalState, Event>| {
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+        let mut state = GlobalState;
+
+        let mut manager = LayerManager::new(vec![
+            Box::new(Tagged(0)),
+            Box::new(Scripted(Some(Change::add(added())))),
+            Box::new(Tagged(4)),
+        ]);
+        manager.set_asleep(2, true);
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(tags(&manager)[..], [0, 0, 1, 2, 3, 4]);
+
+        let mut manager = LayerManager::new(vec![
+            Box::new(Tagged(0)),
+            Box::new(Scripted(Some(Change::replace(added())))),
+        ]);
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(tags(&manager)[..], [0, 1, 2, 3]);
+    }
+
+    pub struct Pooled {
+        id: u32,
+    }
+
+    impl Layer<GlobalState, Event> for Pooled {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            match event {
+                Event::Input => Change::replace_one(Pooled { id: self.id + 10 }),
+                Event::Idle => Change::none(),
+                Event::Exit => Change::remove(),
+            }
+        }
+
+        fn tags(&self) -> u32 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn reuse_removed() {
+        let mut manager =
+            LayerManager::new(vec![Box::new(Pooled { id: 1 }), Box::new(Pooled { id: 2 })]);
+        let mut state = GlobalState;
+        let tags = |manager: &LayerManager<GlobalState, Event>| {
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+
+        let mut removed = manager.update_taking_removed(&mut state, Event::Input);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].tags(), 2);
+        assert_eq!(tags(&manager)[..], [1, 12]);
+
+        manager.push(removed.remove(0));
+        manager.update(&mut state, Event::Input);
+        assert_eq!(tags(&manager)[..], [1, 12, 12]);
+
+        let removed = manager.update_taking_removed(&mut state, Event::Exit);
+        assert_eq!(removed[0].tags(), 12);
+        assert_eq!(tags(&manager)[..], [1, 12]);
+    }
+
+    pub struct Toolbar {
+        enabled: bool,
+        clicks: alloc::rc::Rc<core::cell::Cell<u32>>,
+        draws: alloc::rc::Rc<core::cell::Cell<u32>>,
+    }
+
+    impl Layer<GlobalState, Event> for Toolbar {
+        fn passive_update(&mut self, _state: &mut GlobalState, _event: &Event) {
+            self.draws.set(self.draws.get() + 1);
+        }
+
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            self.clicks.set(self.clicks.get() + 1);
+            Change::none()
+        }
+
+        fn enabled(&self) -> bool {
+            self.enabled
+        }
+
+        fn set_enabled(&mut self, enabled: bool) {
+            self.enabled = enabled;
+        }
+    }
+
+    #[test]
+    fn enabled() {
+        let clicks = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let draws = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let mut manager = LayerManager::new(vec![
+            Box::new(MainLayer),
+            Box::new(Toolbar {
+                enabled: true,
+                clicks: clicks.clone(),
+                draws: draws.clone(),
+            }),
+        ]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!((clicks.get(), draws.get()), (1, 1));
+        assert_eq!(manager.len(), 2);
+
+        manager.set_enabled(1, false);
+        assert!(!manager.iter().nth(1).unwrap().enabled());
+        manager.update(&mut state, Event::Exit);
+        assert_eq!((clicks.get(), draws.get()), (1, 1));
+        assert_eq!(manager.len(), 1);
+
+        manager.set_enabled(0, true);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!((clicks.get(), draws.get()), (2, 2));
+
+        let hidden = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let mut manager = LayerManager::new(vec![Box::new((
+            Toolbar {
+                enabled: true,
+                clicks: clicks.clone(),
+                draws: draws.clone(),
+            },
+            Toolbar {
+                enabled: false,
+                clicks: hidden.clone(),
+                draws: hidden.clone(),
+            },
+        ))]);
+        manager.update(&mut state, Event::Exit);
+        assert_eq!((clicks.get(), draws.get()), (3, 3));
+        assert_eq!(hidden.get(), 0);
+    }
+
+    pub struct Image {
+        pixels: Vec<u8>,
+    }
+
+    pub struct Viewer {
+        shown: alloc::rc::Rc<core::cell::RefCell<Option<Image>>>,
+    }
+
+    impl Layer<GlobalState, Image> for Viewer {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Image,
+        ) -> Change<GlobalState, Image> {
+            Change::pass()
+        }
+
+        fn take_event(
+            &mut self,
+            _state: &mut GlobalState,
+            event: Image,
+        ) -> Result<Change<GlobalState, Image>, Image> {
+            if event.pixels.is_empty() {
+                return Err(event);
+            }
+            *self.shown.borrow_mut() = Some(event);
+            Ok(Change::none())
+        }
+    }
+
+    #[test]
+    fn update_owned() {
+        let shown = alloc::rc::Rc::new(core::cell::RefCell::new(None));
+        let viewer: Box<dyn Layer<GlobalState, Image>> = Box::new(Viewer {
+            shown: shown.clone(),
+        });
+        let mut manager = LayerManager::from_layer(viewer);
+        let mut state = GlobalState;
+
+        let pixels = vec![1, 2, 3];
+        let address = pixels.as_ptr();
+        assert!(manager.update_owned(&mut state, Image { pixels }).is_none());
+        assert_eq!(shown.borrow().as_ref().unwrap().pixels.as_ptr(), address);
+        assert!(manager
+            .update_owned(&mut state, Image { pixels: Vec::new() })
+            .is_some());
+
+        struct Curtain;
+
+        impl Layer<GlobalState, Image> for Curtain {
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                _event: &Image,
+            ) -> Change<GlobalState, Image> {
+                Change::pass()
+            }
+
+            fn is_opaque(&self) -> bool {
+                true
+            }
+        }
+
+        *shown.borrow_mut() = None;
+        manager.push(Box::new(Curtain));
+        manager.set_input_occlusion(true);
+        assert!(manager
+            .update_owned(&mut state, Image { pixels: vec![4] })
+            .is_some());
+        assert!(shown.borrow().is_none());
+    }
+
+    #[test]
+    fn broadcast() {
+        let clicks = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let draws = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let mut manager = LayerManager::new(vec![
+            Box::new(Toolbar {
+                enabled: true,
+                clicks: clicks.clone(),
+                draws: draws.clone(),
+            }),
+            Box::new(Pooled { id: 1 }),
+            Box::new(Pooled { id: 2 }),
+        ]);
+        let mut state = GlobalState;
+        let tags = |manager: &LayerManager<GlobalState, Event>| {
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+
+        manager.broadcast(&mut state, &Event::Input);
+        assert_eq!(tags(&manager)[..], [0, 11, 12]);
+
+        manager.push(Box::new(Scripted(Some(Change::add_bottom([
+            Box::new(Tagged(7)) as Box<dyn Layer<GlobalState, Event>>,
+        ])))));
+        manager.broadcast(&mut state, &Event::Exit);
+        assert_eq!(tags(&manager)[..], [7, 0, 0]);
+        assert_eq!((clicks.get(), draws.get()), (2, 2));
+    }
+
+    pub enum Routed {
+        Click,
+        Resize,
+        Text,
+    }
+
+    impl EventRouting for Routed {
+        fn routing(&self) -> Routing {
+            match self {
+                Routed::Click => Routing::Consume,
+                Routed::Resize => Routing::Broadcast,
+                Routed::Text => Routing::TopOnly,
+            }
+        }
+    }
+
+    #[test]
+    fn update_routed() {
+        use testing::{calls, CallKind, CallLog, RecordingLayer};
+
+        let log = CallLog::default();
+        let summary = |_: &Routed| ();
+        let mut manager: LayerManager<GlobalState, Routed> = LayerManager::new(vec![
+            Box::new(RecordingLayer::new("bottom", &log, summary)),
+            Box::new(RecordingLayer::new("middle", &log, summary)),
+            Box::new(RecordingLayer::new("top", &log, summary).with_script(vec![
+                Change::pass(),
+                Change::none(),
+                Change::pass(),
+            ])),
+        ]);
+        let mut state = GlobalState;
+        let updated = || {
+            let updated = calls(&log, None, CallKind::Update)
+                .iter()
+                .map(|call| call.layer)
+                .collect::<Vec<_>>();
+            log.borrow_mut().clear();
+            updated
+        };
+
+        assert_eq!(
+            manager.update_routed(&mut state, Routed::Click),
+            Dispatch::Consumed
+        );
+        assert_eq!(updated()[..], ["top", "middle"]);
+
+        assert_eq!(
+            manager.update_routed(&mut state, Routed::Resize),
+            Dispatch::Consumed
+        );
+        assert_eq!(updated()[..], ["top", "middle", "bottom"]);
+
+        let observed = alloc::rc::Rc::new(core::cell::RefCell::new(Vec::new()));
+        let changes = observed.clone();
+        manager.set_observer(Box::new(move |index, action| {
+            changes.borrow_mut().push((index, *action))
+        }));
+        assert_eq!(
+            manager.update_routed(&mut state, Routed::Text),
+            Dispatch::PassedThrough
+        );
+        assert_eq!(updated()[..], ["top"]);
+        assert_eq!(observed.borrow()[..], [(2, ChangeAction::Pass)]);
+    }
+
+    pub struct Shell;
+
+    impl Layer<GlobalState, Event> for Shell {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            _event: &Event,
+        ) -> Change<GlobalState, Event> {
+            Change::remove()
+        }
+
+        fn sticky(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn sticky() {
+        let mut manager = LayerManager::new(vec![
+            Box::new(Shell),
+            Box::new(MainLayer),
+            Box::new(Scripted(Some(Change::close()))),
+        ]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(manager.len(), 1);
+        assert!(manager.pop(&mut state).is_none());
+        assert!(manager.remove_at(0, &mut state).is_none());
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(manager.len(), 1);
+
+        manager.push(Box::new(TopLayer));
+        assert!(manager.pop(&mut state).is_some());
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn deferred_changes() {
+        let passing = || -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Scripted(Some(Change {
+                add: vec![Box::new(Tagged(5))],
+                action: ChangeAction::Pass,
+                placement: Placement::Above,
+                dirty: false,
+            })))
+        };
+        let tags = |change| {
+            let mut manager = LayerManager::new(vec![
+                Box::new(Tagged(1)),
+                Box::new(Scripted(Some(change))),
+                passing(),
+            ]);
+            manager.update(&mut GlobalState, Event::Idle);
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(tags(Change::remove())[..], [1, 0, 5]);
+        assert_eq!(
+            tags(Change::add_bottom([
+                Box::new(Tagged(7)) as Box<dyn Layer<GlobalState, Event>>
+            ]))[..],
+            [7, 1, 0, 0, 5]
+        );
+        assert_eq!(tags(Change::replace_one(Tagged(3)))[..], [1, 3, 0, 5]);
+        assert!(tags(Change::close()).is_empty());
+    }
+
+    pub struct Recorder {
+        id: u32,
+        updates: alloc::rc::Rc<core::cell::Cell<u32>>,
+    }
+
+    impl Layer<GlobalState, Event> for Recorder {
+        fn update(
+            &mut self,
+            _state: &mut GlobalState,
+            event: &Event,
+        ) -> Change<GlobalState, Event> {
+            self.updates.set(self.updates.get() + 1);
+            match event {
+                Event::Exit => Change::remove(),
+                _ => Change::pass(),
+            }
+        }
+
+        fn tags(&self) -> u32 {
+            self.id
+        }
+
+        fn is_persistent(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn persistent() {
+        let updates = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let recorder = |id| -> Box<dyn Layer<GlobalState, Event>> {
+            Box::new(Recorder {
+                id,
+                updates: updates.clone(),
+            })
+        };
+        let mut manager = LayerManager::new(vec![
+            recorder(1),
+            Box::new(MainLayer),
+            recorder(2),
+            Box::new(TopLayer),
+            Box::new(Scripted(Some(Change::close()))),
+        ]);
+        let mut state = GlobalState;
+        let tags = |manager: &LayerManager<GlobalState, Event>| {
+            manager.iter().map(|layer| layer.tags()).collect::<Vec<_>>()
+        };
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(tags(&manager)[..], [1, 2]);
+        assert!(manager.is_active());
+
+        manager.update(&mut state, Event::Input);
+        assert_eq!(updates.get(), 2);
+
+        manager.update(&mut state, Event::Exit);
+        assert_eq!(tags(&manager)[..], [1]);
+        assert_eq!(updates.get(), 3);
+    }
+
+    #[test]
+    fn unhandled() {
+        let unhandled = alloc::rc::Rc::new(core::cell::Cell::new(0));
+        let mut manager = LayerManager::new(vec![Box::new(TopLayer), Box::new(TopLayer)]);
+        let counter = unhandled.clone();
+        manager.set_unhandled_handler(move |_state, event| {
+            assert!(matches!(event, Event::Input));
+            counter.set(counter.get() + 1);
+        });
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(unhandled.get(), 0);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(unhandled.get(), 1);
+
+        manager.truncate(0);
+        manager.update(&mut state, Event::Input);
+        assert_eq!(unhandled.get(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    mod save {
+        use super::*;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        pub struct Score {
+            points: u32,
+            combo: u8,
+        }
+
+        impl Layer<GlobalState, Event> for Score {
+            fn update(
+                &mut self,
+                _state: &mut GlobalState,
+                _event: &Event,
+            ) -> Change<GlobalState, Event> {
+                self.points += 1;
+                Change::none()
+            }
+
+            fn save(&self) -> Option<Vec<u8>> {
+                postcard::to_allocvec(self).ok()
+            }
+        }
+
+        #[test]
+        fn save_layers() {
+            let mut manager = LayerManager::new(vec![
+                Box::new(MainLayer),
+                Box::new(Score {
+                    points: 2,
+                    combo: 3,
+                }),
+            ]);
+            let mut state = GlobalState;
+            manager.update(&mut state, Event::Idle);
+
+            let saved = manager.save_layers();
+            assert_eq!(saved.len(), 1);
+            let restored: Score = postcard::from_bytes(&saved[0]).unwrap();
+            assert_eq!(
+                restored,
+                Score {
+                    points: 3,
+                    combo: 3
+                }
+            );
+        }
+    }
+
+    #[cfg(feature = "defmt")]
+    mod defmt_logger {
+        use super::*;
+
+        #[defmt::global_logger]
+        struct Logger;
+
+        unsafe impl defmt::Logger for Logger {
+            fn acquire() {}
+            unsafe fn flush() {}
+            unsafe fn release() {}
+            unsafe fn write(_bytes: &[u8]) {}
+        }
+
+        defmt::timestamp!("");
+
+        #[test]
+        fn logging() {
+            let mut manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+            let mut state = GlobalState;
+
+            manager.update(&mut state, Event::Input);
+            manager.update(&mut state, Event::Exit);
+            manager.update(&mut state, Event::Idle);
+            assert_eq!(manager.len(), 2);
+        }
+    }
+
+    #[cfg(feature = "record")]
+    mod record {
+        use super::*;
+
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum Command {
+            Open,
+            Close,
+            Noop,
+        }
+
+        #[derive(Default, Debug, PartialEq)]
+        pub struct Session {
+            opened: u32,
+            closed: u32,
+            idle: u32,
+        }
+
+        pub struct Menu;
+
+        impl Layer<Session, Command> for Menu {
+            fn update(
+                &mut self,
+                state: &mut Session,
+                command: &Command,
+            ) -> Change<Session, Command> {
+                match command {
+                    Command::Open => {
+                        state.opened += 1;
+                        Change::add_one(Menu)
+                    }
+                    Command::Close => {
+                        state.closed += 1;
+                        Change::remove()
+                    }
+                    Command::Noop => {
+                        state.idle += 1;
+                        Change::none()
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn from_recording() {
+            let mut manager = LayerManager::new(vec![Box::new(Menu)]);
+            let mut state = Session::default();
+            manager.update(&mut state, Command::Open);
+            manager.start_recording();
+            assert!(manager.is_recording());
+            for command in [Command::Noop, Command::Open, Command::Open, Command::Close] {
+                manager.update(&mut state, command);
+            }
+            let recording = manager.take_recording();
+            assert!(!manager.is_recording());
+            assert_eq!((recording.len(), manager.len()), (4, 3));
+
+            let mut replayed_state = Session::default();
+            let mut replayed = LayerManager::from_recording(
+                vec![Box::new(Menu)],
+                core::iter::once(Command::Open).chain(recording),
+                &mut replayed_state,
+            );
+            assert_eq!(replayed.len(), manager.len());
+            assert_eq!(replayed_state, state);
+            assert!(replayed.take_recording().is_empty());
+        }
+
+        #[test]
+        fn replay() {
+            let mut manager = LayerManager::new(vec![Box::new(Menu)]);
+            let mut state = Session::default();
+            manager.start_recording();
+            for command in [Command::Open, Command::Open, Command::Noop, Command::Close] {
+                manager.update(&mut state, command);
+            }
+            let tape = manager.stop_recording();
+            assert_eq!(
+                tape.0,
+                [Command::Open, Command::Open, Command::Noop, Command::Close]
+            );
+            assert_eq!(manager.stop_recording(), EventTape(Vec::new()));
+
+            let mut rebuilt = LayerManager::new(vec![Box::new(Menu)]);
+            let mut rebuilt_state = Session::default();
+            assert_eq!(rebuilt.replay(&mut rebuilt_state, &tape), 4);
+            assert_eq!(rebuilt.len(), manager.len());
+            assert_eq!(rebuilt_state, state);
+
+            let closing = EventTape(vec![
+                Command::Close,
+                Command::Close,
+                Command::Close,
+                Command::Open,
+            ]);
+            assert_eq!(rebuilt.replay(&mut rebuilt_state, &closing), 2);
+            assert!(!rebuilt.is_active());
+            assert_eq!(rebuilt_state.closed, 3);
+        }
+
+        #[test]
+        fn replay_filtered() {
+            let shift = || -> EventFilter<Session, Command> {
+                Box::new(|_state, command| {
+                    Some(match command {
+                        Command::Noop => Command::Open,
+                        _ => Command::Close,
+                    })
+                })
+            };
+            let mut manager = LayerManager::new(vec![Box::new(Menu)]);
+            manager.set_event_filter(shift());
+            let mut state = Session::default();
+            manager.start_recording();
+            manager.update(&mut state, Command::Noop);
+            manager.update(&mut state, Command::Open);
+            let tape = manager.stop_recording();
+            assert_eq!(tape.0, [Command::Open, Command::Close]);
+
+            let mut rebuilt = LayerManager::new(vec![Box::new(Menu)]);
+            rebuilt.set_event_filter(shift());
+            let mut rebuilt_state = Session::default();
+            assert_eq!(rebuilt.replay(&mut rebuilt_state, &tape), 2);
+            assert_eq!(rebuilt.len(), manager.len());
+            assert_eq!(rebuilt_state, state);
+        }
+
+        impl EventRouting for Command {
+            fn routing(&self) -> Routing {
+                match self {
+                    Command::Noop => Routing::Broadcast,
+                    _ => Routing::Consume,
+                }
+            }
+        }
+
+        #[test]
+        fn replay_routed() {
+            let mut manager = LayerManager::new(vec![Box::new(Menu)]);
+            let mut state = Session::default();
+            manager.start_recording();
+            for command in [Command::Open, Command::Noop, Command::Close, Command::Noop] {
+                manager.update_routed(&mut state, command);
+            }
+            manager.broadcast(&mut state, &Command::Noop);
+            let tape = manager.stop_recording();
+            assert_eq!(tape.0.len(), 4);
+            assert_eq!(state.idle, 4);
+
+            let mut rebuilt = LayerManager::new(vec![Box::new(Menu)]);
+            let mut rebuilt_state = Session::default();
+            assert_eq!(rebuilt.replay_routed(&mut rebuilt_state, &tape), 4);
+            assert_eq!(rebuilt.len(), manager.len());
+            assert_eq!(rebuilt_state.idle, 3);
+            assert_eq!(
+                (rebuilt_state.opened, rebuilt_state.closed),
+                (state.opened, state.closed)
+            );
+        }
+    }
+
+    #[cfg(feature = "allocator_api")]
+    mod allocator {
+        use super::*;
+        use core::{
+            alloc::{AllocError, Layout},
+            cell::{Cell, UnsafeCell},
+            ptr::NonNull,
+        };
+
+        const SIZE: usize = 4096;
+
+        /// Hands out memory from a fixed buffer and never frees it.
+        struct Bump {
+            memory: UnsafeCell<[u8; SIZE]>,
+            used: Cell<usize>,
+        }
+
+        unsafe impl Allocator for &Bump {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                let base = self.memory.get() as *mut u8;
+                let unaligned = base as usize + self.used.get();
+                let start = unaligned.next_multiple_of(layout.align()) - base as usize;
+                let end = start + layout.size();
+                if end > SIZE {
+                    return Err(AllocError);
+                }
+                self.used.set(end);
+                let memory = NonNull::new(unsafe { base.add(start) }).ok_or(AllocError)?;
+                Ok(NonNull::slice_from_raw_parts(memory, layout.size()))
+            }
+
+            unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+        }
+
+        #[test]
+        fn bump_allocator() {
+            let bump = Bump {
+                memory: UnsafeCell::new([0; SIZE]),
+                used: Cell::new(0),
+            };
+            let mut manager = LayerManager::new_in(vec![Box::new(MainLayer)], &bump);
+            let mut state = GlobalState;
+            let initial = bump.used.get();
+            assert!(initial > 0);
+
+            for _ in 0..8 {
+                manager.update(&mut state, Event::Input);
+            }
+            assert_eq!(manager.len(), 9);
+            assert!(bump.used.get() > initial);
+
+            while manager.is_active() {
+                manager.update(&mut state, Event::Exit);
+            }
         }
     }
 }
-*/
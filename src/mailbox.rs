@@ -0,0 +1,148 @@
+//! Requesting changes of a layer manager from other threads.
+//!
+//! The commands sent through a `CommandSender` are applied at the start of the next update of the manager, in the order they have been sent.
+//! So layers pushed by a command already receive the event of this update.
+//! Commands sent while an update is running are applied at the next update.
+
+use crate::{Allocator, Layer, LayerManager};
+use alloc::boxed::Box;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Creates a layer on the thread of the layer manager.
+pub type LayerFactory<S, E> = Box<dyn FnOnce() -> Box<dyn Layer<S, E>> + Send>;
+
+pub(crate) enum Command<S, E> {
+    Push(LayerFactory<S, E>),
+    Remove(&'static str),
+    Clear,
+}
+
+pub(crate) type Mailbox<S, E> = (Sender<Command<S, E>>, Receiver<Command<S, E>>);
+
+/// The error returned when sending a command to a layer manager, which has already been dropped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Disconnected;
+
+/// A handle for sending commands to a layer manager from other threads.
+///
+/// It's created by `LayerManager::mailbox`.
+pub struct CommandSender<S, E> {
+    sender: Sender<Command<S, E>>,
+}
+
+impl<S, E> Clone for CommandSender<S, E> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<S, E> CommandSender<S, E> {
+    /// Push the layer created by `factory` on top of the stack.
+    ///
+    /// The factory is called on the thread of the manager, so the layer itself doesn't need to be `Send`.
+    pub fn push(
+        &self,
+        factory: impl FnOnce() -> Box<dyn Layer<S, E>> + Send + 'static,
+    ) -> Result<(), Disconnected> {
+        self.send(Command::Push(Box::new(factory)))
+    }
+
+    /// Remove all layers with the specified `Layer::name` like `LayerManager::remove_by_tag`.
+    pub fn remove(&self, name: &'static str) -> Result<(), Disconnected> {
+        self.send(Command::Remove(name))
+    }
+
+    /// Remove all layers except sticky and persistent ones like `Change::clear`.
+    pub fn clear(&self) -> Result<(), Disconnected> {
+        self.send(Command::Clear)
+    }
+
+    fn send(&self, command: Command<S, E>) -> Result<(), Disconnected> {
+        self.sender.send(command).map_err(|_| Disconnected)
+    }
+}
+
+impl<S, E, A: Allocator> LayerManager<S, E, A> {
+    /// Get a handle for requesting changes from other threads.
+    ///
+    /// See the `mailbox` module for when the commands are applied.
+    pub fn mailbox(&self) -> CommandSender<S, E> {
+        CommandSender {
+            sender: self.mailbox.0.clone(),
+        }
+    }
+
+    pub(crate) fn apply_commands(&mut self) {
+        let mut applied = false;
+        while let Ok(command) = self.mailbox.1.try_recv() {
+            applied = true;
+            match command {
+                Command::Push(factory) => {
+                    self.insert_added(self.layers.len(), alloc::vec![factory()]);
+                }
+                Command::Remove(name) => {
+                    for index in (0..self.layers.len()).rev() {
+                        if self.layers[index].layer.name() == name {
+                            self.retire(index);
+                        }
+                    }
+                }
+                Command::Clear => self.clear_layers(),
+            }
+        }
+        if applied {
+            self.refresh_cover();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Change;
+    use alloc::vec;
+
+    pub struct Counter;
+
+    impl Layer<u32, ()> for Counter {
+        fn update(&mut self, state: &mut u32, _event: &()) -> Change<u32, ()> {
+            *state += 1;
+            Change::pass()
+        }
+
+        fn name(&self) -> &'static str {
+            "counter"
+        }
+    }
+
+    #[test]
+    fn mailbox() {
+        let mut manager = LayerManager::new(vec![Box::new(Counter)]);
+        let mut count = 0;
+
+        let sender = manager.mailbox();
+        std::thread::spawn(move || sender.push(|| Box::new(Counter)).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(manager.len(), 1);
+        manager.update(&mut count, ());
+        assert_eq!(manager.len(), 2);
+        assert_eq!(count, 2);
+
+        let sender = manager.mailbox();
+        sender.remove("counter").unwrap();
+        sender.push(|| Box::new(Counter)).unwrap();
+        manager.update(&mut count, ());
+        assert_eq!(manager.len(), 1);
+        assert_eq!(count, 3);
+
+        sender.clear().unwrap();
+        manager.update(&mut count, ());
+        assert!(manager.is_empty());
+
+        drop(manager);
+        assert_eq!(sender.clear(), Err(Disconnected));
+    }
+}
@@ -0,0 +1,53 @@
+//! The stack of the simpler layer managers, which only know the basic changes.
+//!
+//! The asynchronous and the borrowed layer managers apply the changes of their layers in the same way, so both use these entries.
+
+use crate::ChangeAction;
+use alloc::{boxed::Box, vec::Vec};
+
+pub(crate) struct Entry<L: ?Sized> {
+    pub(crate) layer: Box<L>,
+    pub(crate) asleep: bool,
+}
+
+impl<L: ?Sized> Entry<L> {
+    pub(crate) fn new(layer: Box<L>) -> Self {
+        Self {
+            layer,
+            asleep: false,
+        }
+    }
+}
+
+/// Apply the change returned by the layer at `i` and return if the dispatch ends.
+///
+/// Clearing removes all layers before the new layers are added.
+pub(crate) fn apply<L: ?Sized>(
+    layers: &mut Vec<Entry<L>>,
+    i: usize,
+    add: Vec<Box<L>>,
+    action: ChangeAction,
+) -> bool {
+    let add_index = if action == ChangeAction::Clear {
+        layers.clear();
+        0
+    } else {
+        i + 1
+    };
+    layers.splice(add_index..add_index, add.into_iter().map(Entry::new));
+    use ChangeAction::*;
+    match action {
+        None | Clear => (),
+        Pass => return false,
+        Remove => {
+            layers.remove(i);
+        }
+        Sleep => layers[i].asleep = true,
+        Wake => {
+            for entry in layers.iter_mut() {
+                entry.asleep = false;
+            }
+        }
+    }
+    true
+}
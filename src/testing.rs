@@ -0,0 +1,260 @@
+//! Utilities for testing layers and stacks.
+//!
+//! Available in the tests of this crate and with the `test-util` feature.
+
+use crate::{Change, Layer, LayerManager};
+use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+/// The kind of call received by a recording layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    /// A call of `update`.
+    Update,
+    /// A call of `passive_update`.
+    Passive,
+}
+
+/// A call received by a recording layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallRecord<R> {
+    /// The name of the layer receiving the call.
+    pub layer: &'static str,
+    /// The kind of call.
+    pub kind: CallKind,
+    /// The summary of the event.
+    pub event: R,
+}
+
+/// A log shared by recording layers and the test inspecting it.
+pub type CallLog<R> = Rc<RefCell<Vec<CallRecord<R>>>>;
+
+/// Return the calls of `kind` in `log` in order, only those received by `layer` if given.
+pub fn calls<R: Clone>(
+    log: &CallLog<R>,
+    layer: Option<&str>,
+    kind: CallKind,
+) -> Vec<CallRecord<R>> {
+    log.borrow()
+        .iter()
+        .filter(|call| call.kind == kind && layer.is_none_or(|layer| call.layer == layer))
+        .cloned()
+        .collect()
+}
+
+/// A layer, which records all calls of `update` and `passive_update` and returns scripted changes.
+pub struct RecordingLayer<S, E, R> {
+    name: &'static str,
+    log: CallLog<R>,
+    summarize: fn(&E) -> R,
+    script: VecDeque<Change<S, E>>,
+}
+
+impl<S, E, R> RecordingLayer<S, E, R> {
+    /// Create a layer recording its calls into `log`, summarizing the events by `summarize`.
+    ///
+    /// Without a script, every `update` returns `Change::none()`.
+    pub fn new(name: &'static str, log: &CallLog<R>, summarize: fn(&E) -> R) -> Self {
+        Self {
+            name,
+            log: log.clone(),
+            summarize,
+            script: VecDeque::new(),
+        }
+    }
+
+    /// Return the scripted changes from the following calls of `update` in order.
+    ///
+    /// When the script is exhausted, `Change::none()` is returned.
+    pub fn with_script(mut self, script: impl IntoIterator<Item = Change<S, E>>) -> Self {
+        self.script.extend(script);
+        self
+    }
+
+    fn record(&self, kind: CallKind, event: &E) {
+        self.log.borrow_mut().push(CallRecord {
+            layer: self.name,
+            kind,
+            event: (self.summarize)(event),
+        });
+    }
+}
+
+impl<S, E: Clone> RecordingLayer<S, E, E> {
+    /// Create a layer recording its calls into `log` with clones of the events.
+    pub fn cloning(name: &'static str, log: &CallLog<E>) -> Self {
+        Self::new(name, log, E::clone)
+    }
+}
+
+impl<S, E, R> Layer<S, E> for RecordingLayer<S, E, R> {
+    fn passive_update(&mut self, _state: &mut S, event: &E) {
+        self.record(CallKind::Passive, event);
+    }
+
+    fn update(&mut self, _state: &mut S, event: &E) -> Change<S, E> {
+        self.record(CallKind::Update, event);
+        self.script.pop_front().unwrap_or_else(Change::none)
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// A driver running a layer manager through a sequence of events, checking the stack after each step.
+///
+/// Every failing expectation panics with the step, the expected and the found value, and the names of all layers.
+pub struct Scenario<S, E> {
+    manager: LayerManager<S, E>,
+    state: S,
+    events: usize,
+}
+
+impl<S, E> Scenario<S, E> {
+    /// Create a scenario for a manager and its state.
+    pub fn new(manager: LayerManager<S, E>, state: S) -> Self {
+        Self {
+            manager,
+            state,
+            events: 0,
+        }
+    }
+
+    /// Update the manager with an event.
+    pub fn event(mut self, event: E) -> Self {
+        self.manager.update(&mut self.state, event);
+        self.events += 1;
+        self
+    }
+
+    /// Expect the stack to contain `depth` layers.
+    #[track_caller]
+    pub fn expect_depth(self, depth: usize) -> Self {
+        if self.manager.len() != depth {
+            self.fail("depth", depth, self.manager.len());
+        }
+        self
+    }
+
+    /// Expect the manager to be inactive.
+    #[track_caller]
+    pub fn expect_inactive(self) -> Self {
+        if self.manager.is_active() {
+            self.fail("depth", 0, self.manager.len());
+        }
+        self
+    }
+
+    /// Expect the name of the top layer to be `name`.
+    ///
+    /// Besides the full name, the last segment of a path is accepted, so type names don't need to be specified with their module.
+    #[track_caller]
+    pub fn expect_top_named(self, name: &str) -> Self {
+        let top = self.manager.iter().last().map(|layer| layer.name());
+        let matches = top.is_some_and(|top| top == name || top.rsplit("::").next() == Some(name));
+        if !matches {
+            self.fail("top layer", Some(name), top);
+        }
+        self
+    }
+
+    /// Expect a check of the layers from bottom to top to succeed.
+    #[track_caller]
+    pub fn expect_layers(
+        self,
+        description: &str,
+        check: impl FnOnce(&[&dyn Layer<S, E>]) -> bool,
+    ) -> Self {
+        let layers: Vec<_> = self.manager.iter().collect();
+        if !check(&layers) {
+            self.fail("layers", description, "mismatch");
+        }
+        self
+    }
+
+    /// Expect a check of the state to succeed.
+    #[track_caller]
+    pub fn expect_state(self, description: &str, check: impl FnOnce(&S) -> bool) -> Self {
+        if !check(&self.state) {
+            self.fail("state", description, "mismatch");
+        }
+        self
+    }
+
+    /// Finish the scenario and return the manager and its state.
+    pub fn into_parts(self) -> (LayerManager<S, E>, S) {
+        (self.manager, self.state)
+    }
+
+    #[track_caller]
+    fn fail(&self, what: &str, expected: impl core::fmt::Debug, found: impl core::fmt::Debug) -> ! {
+        let names: Vec<_> = self.manager.iter().map(|layer| layer.name()).collect();
+        panic!(
+            "after {} events: unexpected {}\n  expected: {:?}\n     found: {:?}\n     stack: {:?}",
+            self.events, what, expected, found, names
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LayerManager;
+    use alloc::{boxed::Box, vec};
+
+    #[test]
+    fn cloning() {
+        let log = CallLog::default();
+        let layer = RecordingLayer::cloning("counter", &log).with_script(vec![Change::pass()]);
+        let mut manager = LayerManager::new(vec![Box::new(layer)]);
+
+        manager.update(&mut (), 1);
+        manager.update(&mut (), 2);
+        assert_eq!(
+            log.borrow()
+                .iter()
+                .map(|call| (call.kind, call.event))
+                .collect::<Vec<_>>()[..],
+            [
+                (CallKind::Update, 1),
+                (CallKind::Passive, 1),
+                (CallKind::Update, 2),
+                (CallKind::Passive, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn scenario() {
+        use crate::tests::{Event, GlobalState, MainLayer, TopLayer};
+
+        let manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        Scenario::new(manager, GlobalState)
+            .event(Event::Idle)
+            .expect_depth(2)
+            .event(Event::Input)
+            .expect_depth(3)
+            .expect_top_named("TopLayer")
+            .expect_layers("main layer at the bottom", |layers| {
+                layers[0].name().ends_with("MainLayer")
+            })
+            .event(Event::Idle)
+            .event(Event::Exit)
+            .event(Event::Exit)
+            .expect_top_named("MainLayer")
+            .event(Event::Exit)
+            .expect_inactive();
+    }
+
+    #[test]
+    #[should_panic(expected = "after 1 events: unexpected depth\n  expected: 2\n     found: 3")]
+    fn scenario_failure() {
+        use crate::tests::{Event, GlobalState, MainLayer, TopLayer};
+
+        let manager = LayerManager::new(vec![Box::new(MainLayer), Box::new(TopLayer)]);
+        Scenario::new(manager, GlobalState)
+            .event(Event::Input)
+            .expect_depth(2);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{Change, ChangeAction, Layer, LayerError, Placement};
+use alloc::{boxed::Box, vec::Vec};
+use core::{any::Any, convert::Infallible};
+
+type Asked<S, E, F> = Result<(Change<S, E>, Option<Box<dyn Any>>), F>;
+
+/// Dispatch an event to the elements from top to bottom, using `ask` to update a single element.
+///
+/// The output is the one of the last asked element returning an output.
+fn update_tuple<S, E, F>(
+    layers: &mut [&mut dyn Layer<S, E>],
+    state: &mut S,
+    event: &E,
+    mut ask: impl FnMut(&mut dyn Layer<S, E>, &mut S, &E) -> Asked<S, E, F>,
+) -> Asked<S, E, F> {
+    let mut add = Vec::new();
+    let mut placement = Placement::Above;
+    let mut dirty = false;
+    let mut output = None;
+    for layer in layers.iter_mut().rev() {
+        if !layer.enabled() {
+            continue;
+        }
+        let (change, produced) = ask(&mut **layer, state, event)?;
+        output = produced.or(output);
+        dirty |= change.dirty;
+        if !change.add.is_empty() {
+            placement = change.placement;
+        }
+        add.extend(change.add);
+        if change.action != ChangeAction::Pass {
+            let change = Change {
+                add,
+                action: change.action,
+                placement,
+                dirty,
+            };
+            return Ok((change, output));
+        }
+    }
+    let change = Change {
+        add,
+        action: ChangeAction::Pass,
+        placement,
+        dirty,
+    };
+    Ok((change, output))
+}
+
+/// Offer the event to the elements from top to bottom, until an element takes it.
+fn take_tuple<S, E>(
+    layers: &mut [&mut dyn Layer<S, E>],
+    state: &mut S,
+    mut event: E,
+) -> Result<Change<S, E>, E> {
+    for layer in layers.iter_mut().rev() {
+        if !layer.enabled() {
+            continue;
+        }
+        event = match layer.take_event(state, event) {
+            Ok(change) => return Ok(change),
+            Err(event) => event,
+        };
+    }
+    Err(event)
+}
+
+macro_rules! tuple_layer {
+    ($top:tt; $($layer:ident $index:tt),*) => {
+        /// A tuple of layers behaves like a small stack of layers, the last element being the top.
+        ///
+        /// Passive updates are done from the first to the last element, including the elements, which don't need them, if any element needs them.
+        /// Disabled elements are skipped in both passes, as if they passed the event.
+        /// Events are dispatched from the last to the first element, until an element doesn't pass the event.
+        /// The action of this element applies to the whole tuple, so removing any element removes the tuple.
+        /// The layers added by all asked elements are added to the stack in the order the elements are asked, and the placement of the last element adding layers is used.
+        /// The output of `update_output` is the one of the last asked element returning an output, and the first error of `try_update` is returned.
+        /// The name, type and saved data of the tuple are the ones of the last element.
+        impl<S, E, $($layer: Layer<S, E>),*> Layer<S, E> for ($($layer,)*) {
+            fn passive_update(&mut self, state: &mut S, event: &E) {
+                $(if self.$index.enabled() {
+                    self.$index.passive_update(state, event);
+                })*
+            }
+
+            fn update(&mut self, state: &mut S, event: &E) -> Change<S, E> {
+                let layers = &mut [$(&mut self.$index as &mut dyn Layer<S, E>),*];
+                match update_tuple(layers, state, event, |layer, state, event| {
+                    Ok::<_, Infallible>((layer.update(state, event), None))
+                }) {
+                    Ok((change, _)) => change,
+                    Err(never) => match never {},
+                }
+            }
+
+            fn update_output(
+                &mut self,
+                state: &mut S,
+                event: &E,
+            ) -> (Change<S, E>, Option<Box<dyn Any>>) {
+                let layers = &mut [$(&mut self.$index as &mut dyn Layer<S, E>),*];
+                match update_tuple(layers, state, event, |layer, state, event| {
+                    Ok::<_, Infallible>(layer.update_output(state, event))
+                }) {
+                    Ok(updated) => updated,
+                    Err(never) => match never {},
+                }
+            }
+
+            fn try_update(&mut self, state: &mut S, event: &E) -> Result<Change<S, E>, LayerError> {
+                let layers = &mut [$(&mut self.$index as &mut dyn Layer<S, E>),*];
+                update_tuple(layers, state, event, |layer, state, event| {
+                    Ok((layer.try_update(state, event)?, None))
+                })
+                .map(|(change, _)| change)
+            }
+
+            fn take_event(&mut self, state: &mut S, event: E) -> Result<Change<S, E>, E> {
+                take_tuple(&mut [$(&mut self.$index as &mut dyn Layer<S, E>),*], state, event)
+            }
+
+            fn on_attach(&mut self) {
+                $(self.$index.on_attach();)*
+            }
+
+            fn on_detach(&mut self) {
+                $(self.$index.on_detach();)*
+            }
+
+            fn on_cover(&mut self) {
+                $(self.$index.on_cover();)*
+            }
+
+            fn on_uncover(&mut self) {
+                $(self.$index.on_uncover();)*
+            }
+
+            fn priority(&self) -> i32 {
+                let mut priority = i32::MIN;
+                $(priority = priority.max(self.$index.priority());)*
+                priority
+            }
+
+            fn tags(&self) -> u32 {
+                0 $(| self.$index.tags())*
+            }
+
+            fn needs_passive(&self) -> bool {
+                false $(|| self.$index.needs_passive())*
+            }
+
+            fn enabled(&self) -> bool {
+                false $(|| self.$index.enabled())*
+            }
+
+            fn set_enabled(&mut self, enabled: bool) {
+                $(self.$index.set_enabled(enabled);)*
+            }
+
+            fn sticky(&self) -> bool {
+                false $(|| self.$index.sticky())*
+            }
+
+            fn is_persistent(&self) -> bool {
+                false $(|| self.$index.is_persistent())*
+            }
+
+            fn is_opaque(&self) -> bool {
+                false $(|| self.$index.is_opaque())*
+            }
+
+            fn on_remove_request(&mut self, state: &mut S) -> bool {
+                true $(&& self.$index.on_remove_request(state))*
+            }
+
+            forward_layer!(|this| this.$top => name, layer_type_id, save);
+        }
+    };
+}
+
+tuple_layer!(1; A 0, B 1);
+tuple_layer!(2; A 0, B 1, C 2);
+tuple_layer!(3; A 0, B 1, C 2, D 3);
+tuple_layer!(4; A 0, B 1, C 2, D 3, F 4);
+tuple_layer!(5; A 0, B 1, C 2, D 3, F 4, G 5);
+tuple_layer!(6; A 0, B 1, C 2, D 3, F 4, G 5, H 6);
+tuple_layer!(7; A 0, B 1, C 2, D 3, F 4, G 5, H 6, I 7);
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{calls, CallKind, CallLog, RecordingLayer};
+    use crate::tests::{reports_error, reports_output, Event, GlobalState, Reporter, TopLayer};
+    use crate::{Change, LayerManager};
+    use alloc::{boxed::Box, vec, vec::Vec};
+
+    fn summary(_event: &Event) {}
+
+    #[test]
+    fn pair() {
+        let log = CallLog::default();
+        let camera = RecordingLayer::new("camera", &log, summary);
+        let gameplay = RecordingLayer::new("gameplay", &log, summary).with_script(vec![
+            Change::pass(),
+            Change::none(),
+            Change::remove(),
+        ]);
+        let mut manager = LayerManager::new(vec![Box::new((camera, gameplay))]);
+        let mut state = GlobalState;
+        let layers = |kind| {
+            calls(&log, None, kind)
+                .iter()
+                .map(|call| call.layer)
+                .collect::<Vec<_>>()
+        };
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(layers(CallKind::Update)[..], ["gameplay", "camera"]);
+        assert_eq!(layers(CallKind::Passive)[..], ["camera", "gameplay"]);
+        log.borrow_mut().clear();
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(layers(CallKind::Update)[..], ["gameplay"]);
+
+        manager.update(&mut state, Event::Idle);
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn triple() {
+        let log = CallLog::default();
+        let bottom = RecordingLayer::new("bottom", &log, summary)
+            .with_script(vec![Change::add_one(TopLayer)]);
+        let middle =
+            RecordingLayer::new("middle", &log, summary).with_script(vec![Change::replace([
+                Box::new(TopLayer) as Box<dyn crate::Layer<GlobalState, Event>>,
+                Box::new(TopLayer),
+            ])]);
+        let top = RecordingLayer::new("top", &log, summary).with_script(vec![Change::pass()]);
+        let mut manager = LayerManager::new(vec![Box::new((bottom, middle, top))]);
+        let mut state = GlobalState;
+
+        manager.update(&mut state, Event::Idle);
+        assert_eq!(manager.len(), 2);
+        assert!(log
+            .borrow()
+            .iter()
+            .all(|call| call.layer != "bottom" || call.kind == CallKind::Passive));
+    }
+
+    #[test]
+    fn forwarding() {
+        let mut pair = (Reporter, TopLayer);
+        assert!(reports_output(&mut pair));
+        assert!(reports_error(&mut pair));
+        assert_eq!(crate::Layer::name(&pair), crate::Layer::name(&TopLayer));
+    }
+}
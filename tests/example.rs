@@ -1,3 +1,5 @@
+#![allow(clippy::single_match)]
+
 enum Event {
     Fixed,           // generated at fixed times
     Click(u16, u16), // generated by click
@@ -76,14 +78,14 @@ impl Layer<State, Event> for Interface {
                     Change::none()
                 }
                 // pause when clicking "Pause"
-                ClickEvent::Pause => Change::add(vec![Box::new(Pause)]),
+                ClickEvent::Pause => Change::add_one(Pause),
                 // remove all layers when clicking "Exit"
                 ClickEvent::Exit => Change::close(),
                 // nothing was clicked, so in case the world supports a click event, just defer it
                 ClickEvent::Nothing => Change::pass(),
             },
             // when trying to quit the game, pause it first
-            Quit => Change::add(vec![Box::new(Pause)]),
+            Quit => Change::add_one(Pause),
             // Defer all other events, so World will handle everything else
             _ => Change::pass(),
         }